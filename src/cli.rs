@@ -0,0 +1,923 @@
+use std::fmt::Display;
+use std::fs;
+use std::rc::Rc;
+use std::str::FromStr;
+use chrono::{Local, NaiveDate};
+
+use crate::{ Data, costs, csv, json, quarantine };
+use crate::money::{ self, Money };
+use crate::sales::{ Report, Sale };
+use crate::store::{ Backend, Change };
+use crate::closet::{ Kind, Sex, Slot, Rgb, Status, Target };
+use crate::sizes::{ Size, SizeSystem };
+use crate::closet::{ ClthBuffer, OutfitBuffer };
+
+pub const EXIT_DATA: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+pub const EXIT_REJECTED: i32 = 3;
+
+pub const USAGE: &str = "\
+Usage: recloset [--data <path>] [--store <text|sqlite>] [--lenient] [--read-only]
+                [--money <symbol|code>] [COMMAND]
+
+Without a command the interactive menu is started.
+
+Options:
+  --data <path>              Closet data file to use. Defaults to
+                             $RECLOSET_DATA, then to
+                             $XDG_CONFIG_HOME/recloset/data.toml.
+  --store <text|sqlite>      How the closet is stored. Defaults to sqlite
+                             for .db, .sqlite and .sqlite3 files and to
+                             text otherwise.
+  --lenient                  Load the data file even if some records are
+                             invalid, moving them to the quarantine.
+  --read-only                Never save the closet, refusing the commands
+                             that change it. Used as well when another
+                             recloset is using the closet.
+  --money <symbol|code>      Show prices with their currency symbol, like
+                             R$ 20,75 (the default), or with their ISO
+                             code, like 20.75 BRL.
+
+Commands:
+  add-clth --kind <name> --sex <male|female|unissex>
+           --size <size> --color <#RRGGBB>
+           --target <keep|donation|sale> [--price <value>]
+           --style <name> [--date <YYYY-MM-DD>]
+           [--cost <value> [--currency <code>]]
+                             Add a clothing of one of the kinds listed by
+                             'kind list' and print its id. The size is a
+                             letter (XXS to XXXL), a kids' age (8Y) or, for
+                             the kinds sized so, a number (42, 9.5 US) or a
+                             waist and inseam (32x34). Its purchase price
+                             is in USD unless --currency is given.
+  list                       List the clothes in use.
+  kind add <name> <slot> [<sizes>]
+                             Define a kind of clothing worn on <slot>: top,
+                             bottom, full-body, outerwear, footwear or
+                             accessory. It is sized in <sizes>: letter,
+                             numeric, shoe or waist, by default shoe for
+                             footwear, waist for bottoms and letter for
+                             the others.
+  kind list                  List the kinds of clothing, chest, leg and
+                             foot being built in.
+  rm <id>                    Remove a clothing and the outfits using it.
+  cost <id> <value> [<currency>]
+                             Set the purchase price of a clothing, in USD
+                             by default.
+  cost <id> none             Clear the purchase price of a clothing.
+  wear <id>                  Count one more wear of a clothing.
+  archive <id> archived [<YYYY-MM-DD>]
+                             Archive a clothing instead of removing it,
+                             dated today by default. The outfits using it
+                             are removed. Clothes sold or donated are
+                             archived by 'sell' and 'donate'.
+  archive list               List the archived clothes.
+  sell <id> <price> [--channel <name>] [--date <YYYY-MM-DD>]
+                             Record the sale of a clothing for <price> to
+                             a buyer or through a channel, dated today by
+                             default. The clothing is archived as sold.
+                             Prices are in USD unless they have a currency,
+                             like $20.75, €20,75, 'R$ 20,75' or 20.75 EUR.
+  sales list                 List the recorded sales.
+  sales report               Show the sales totals per month and style.
+  sales costs                Show the cost per wear of the clothes with a
+                             purchase price and what they lost on resale.
+  donate <id>... --to <recipient> [--date <YYYY-MM-DD>]
+                             Donate clothes marked for donation as one
+                             batch, dated today by default, and print its
+                             id. The clothes are archived as donated.
+  donation list              List the donations.
+  donation receipt <id> [<file>]
+                             Write the Markdown receipt of a donation to
+                             <file> or stdout.
+  outfit add <id>...         Add an outfit and print its id. An outfit has
+                             one footwear, either a top and a bottom or a
+                             full-body clothing, and optionally an
+                             outerwear and accessories.
+  outfit rm <id>             Remove an outfit.
+  outfit list                List all outfits.
+  export-json [<file>]       Write the closet as JSON to <file> or stdout.
+  import-json <file>         Replace the closet with the JSON in <file>.
+  export-csv [<file>]        Write the clothes in use as CSV to <file> or
+                             stdout.
+  import-csv <file>          Add the clothes of the CSV <file> and print
+                             their ids.
+  quarantine list            List the quarantined records and why they
+                             were rejected.
+  quarantine retry <n>       Validate the quarantined record <n> again and
+                             restore it if it is valid now.
+  quarantine discard <n>     Delete the quarantined record <n>.
+  help                       Show this message.";
+
+#[derive(Debug)]
+pub struct CliErr {
+    pub code: i32,
+    pub msg: String,
+}
+
+impl CliErr {
+    pub fn usage(msg: &str) -> CliErr {
+        CliErr { code: EXIT_USAGE, msg: String::from(msg) }
+    }
+
+    pub fn rejected(msg: &str) -> CliErr {
+        CliErr { code: EXIT_REJECTED, msg: String::from(msg) }
+    }
+}
+
+impl Display for CliErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", &self.msg)
+    }
+}
+
+pub enum Command {
+    /// `kind` is resolved against the kinds of the closet once it is loaded.
+    AddClth {
+        buffer: ClthBuffer,
+        kind: String,
+        style: String,
+        date: NaiveDate,
+        cost: Option<Money>,
+    },
+    ListClths,
+    AddKind(Kind),
+    ListKinds,
+    RemoveClth(u32),
+    SetCost(u32, Option<Money>),
+    WearClth(u32),
+    ArchiveClth(u32, Status),
+    ListArchived,
+    Sell(Sale),
+    ListSales,
+    SalesReport,
+    CostReport,
+    Donate { ids: Vec<u32>, recipient: String, date: NaiveDate },
+    ListDonations,
+    Receipt(u32, Option<String>),
+    AddOutfit(Vec<u32>),
+    RemoveOutfit(u32),
+    ListOutfits,
+    ExportJson(Option<String>),
+    ImportJson(String),
+    ExportCsv(Option<String>),
+    ImportCsv(String),
+    ListQuarantine,
+    RetryQuarantined(usize),
+    DiscardQuarantined(usize),
+    Help,
+}
+
+impl Command {
+    /// Whether the command changes the closet, which is refused when it is
+    /// open read-only.
+    pub fn mutates(&self) -> bool {
+        !matches!(self, Command::ListClths | Command::ListKinds | Command::ListArchived
+            | Command::ListSales | Command::SalesReport | Command::CostReport
+            | Command::ListDonations | Command::Receipt(..) | Command::ListOutfits
+            | Command::ExportJson(_) | Command::ExportCsv(_) | Command::ListQuarantine
+            | Command::Help)
+    }
+}
+
+fn parse_id(value: &str) -> Result<u32, CliErr> {
+    value.parse().map_err(|_| {
+        CliErr::usage(&format!("'{}' is not a valid id.", value))
+    })
+}
+
+fn parse_field<T>(flag: &str, value: Option<String>) -> Result<T, CliErr>
+    where T: FromStr<Err = &'static str>
+{
+    match value {
+        Some(value) => T::from_str(&value)
+            .map_err(|msg| CliErr::usage(&format!("--{}: {}", flag, msg))),
+        None => Err(CliErr::usage(&format!("Missing '--{}' option.", flag))),
+    }
+}
+
+fn parse_add_clth(args: &[String]) -> Result<Command, CliErr> {
+    let mut kind = None;
+    let mut sex = None;
+    let mut size = None;
+    let mut color = None;
+    let mut target = None;
+    let mut price = None;
+    let mut style = None;
+    let mut date = None;
+    let mut cost = None;
+    let mut currency = None;
+
+    let mut iter = args.iter();
+    while let Some(flag) = iter.next() {
+        let slot = match flag.as_str() {
+            "--cost" => &mut cost,
+            "--currency" => &mut currency,
+            "--kind" => &mut kind,
+            "--sex" => &mut sex,
+            "--size" => &mut size,
+            "--color" => &mut color,
+            "--target" => &mut target,
+            "--price" => &mut price,
+            "--style" => &mut style,
+            "--date" => &mut date,
+            _ => return Err(CliErr::usage(&format!("Unknown option '{}'.", flag))),
+        };
+        match iter.next() {
+            Some(value) => *slot = Some(value.clone()),
+            None => return Err(CliErr::usage(&format!("Missing value for '{}'.", flag))),
+        }
+    }
+
+    let kind = match kind {
+        Some(name) if !name.trim().is_empty() => String::from(name.trim()),
+        _ => return Err(CliErr::usage("Missing '--kind' option.")),
+    };
+
+    let mut buffer = ClthBuffer::new();
+    buffer.sex = Some(parse_field::<Sex>("sex", sex)?);
+    buffer.size = Some(parse_field::<Size>("size", size)?);
+
+    let color = match color {
+        Some(value) => Rgb::try_from_hex(value.trim_start_matches('#')),
+        None => return Err(CliErr::usage("Missing '--color' option.")),
+    };
+    buffer.color = match color {
+        Some(color) => Some(color),
+        None => return Err(CliErr::usage("--color: Invalid color.")),
+    };
+
+    buffer.target = match (target.as_deref(), price) {
+        (Some("sale"), Some(price)) => Some(Target::Sale(parse_field("price", Some(price))?)),
+        (Some("sale"), None) => {
+            return Err(CliErr::usage("A sale target requires '--price'."))
+        },
+        (_, Some(_)) => {
+            return Err(CliErr::usage("'--price' is only valid for a sale target."))
+        },
+        (value, None) => Some(parse_field::<Target>(
+            "target", value.map(String::from))?),
+    };
+
+    let style = match style {
+        Some(name) if !name.trim().is_empty() => name.trim().to_lowercase(),
+        _ => return Err(CliErr::usage("Missing '--style' option.")),
+    };
+
+    let date = match date {
+        Some(value) => match NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+            Ok(date) => date,
+            Err(_) => return Err(CliErr::usage("--date: Invalid date.")),
+        },
+        None => Local::today().naive_local(),
+    };
+
+    let cost = match (cost, currency) {
+        (Some(value), currency) => Some(parse_cost(&value, currency.as_deref())?),
+        (None, Some(_)) => return Err(CliErr::usage("'--currency' requires '--cost'.")),
+        (None, None) => None,
+    };
+
+    Ok(Command::AddClth { buffer, kind, style, date, cost })
+}
+
+fn parse_cost(value: &str, currency: Option<&str>) -> Result<Money, CliErr> {
+    match currency {
+        Some(currency) => parse_price(&format!("{} {}", value, currency)),
+        None => parse_price(value),
+    }
+}
+
+fn parse_set_cost(args: &[String]) -> Result<Command, CliErr> {
+    match args {
+        [id, none] if none == "none" => Ok(Command::SetCost(parse_id(id)?, None)),
+        [id, value] => Ok(Command::SetCost(parse_id(id)?, Some(parse_cost(value, None)?))),
+        [id, value, currency] => {
+            Ok(Command::SetCost(parse_id(id)?, Some(parse_cost(value, Some(currency))?)))
+        },
+        _ => Err(CliErr::usage("'cost' expects a clothing id and a price or 'none'.")),
+    }
+}
+
+fn parse_kind(args: &[String]) -> Result<Command, CliErr> {
+    match args {
+        [sub] if sub == "list" => Ok(Command::ListKinds),
+        [sub, name, slot, sizes @ ..] if sub == "add" && sizes.len() <= 1 => {
+            let slot = Slot::from_str(slot).map_err(|msg| {
+                CliErr::usage(&format!("'{}': {}", slot, msg))
+            })?;
+            let mut kind = Kind::new(name, slot);
+            if let [sizes] = sizes {
+                kind.sizes = SizeSystem::from_str(sizes).map_err(|msg| {
+                    CliErr::usage(&format!("'{}': {}", sizes, msg))
+                })?;
+            }
+            Ok(Command::AddKind(kind))
+        },
+        _ => Err(CliErr::usage("'kind' expects 'add <name> <slot> [<sizes>]' or 'list'.")),
+    }
+}
+
+fn parse_outfit(args: &[String]) -> Result<Command, CliErr> {
+    match args.split_first() {
+        Some((sub, rest)) if sub == "add" => {
+            if rest.is_empty() {
+                return Err(CliErr::usage("'outfit add' expects clothing ids."));
+            }
+            let ids = rest.iter().map(|id| parse_id(id)).collect::<Result<_, _>>()?;
+            Ok(Command::AddOutfit(ids))
+        },
+        Some((sub, [id])) if sub == "rm" => Ok(Command::RemoveOutfit(parse_id(id)?)),
+        Some((sub, [])) if sub == "list" => Ok(Command::ListOutfits),
+        _ => Err(CliErr::usage("Expected 'outfit add', 'outfit rm' or 'outfit list'.")),
+    }
+}
+
+fn parse_archive(args: &[String]) -> Result<Command, CliErr> {
+    let (id, status, date) = match args {
+        [sub] if sub == "list" => return Ok(Command::ListArchived),
+        [id, status] => (id, status, Local::today().naive_local().to_string()),
+        [id, status, date] => (id, status, date.clone()),
+        _ => return Err(CliErr::usage("Expected 'archive <id> archived' or 'archive list'.")),
+    };
+
+    // sold and donated clothes need their sale or donation recorded.
+    match status.to_lowercase().as_str() {
+        "archived" => (),
+        "sold" => return Err(CliErr::usage("Use 'sell' to archive a clothing as sold.")),
+        "donated" => return Err(CliErr::usage("Use 'donate' to archive clothes as donated.")),
+        _ => return Err(CliErr::usage("Expected a status of archived.")),
+    }
+    let status = Status::from_str(&format!("archived on {}", date)).map_err(CliErr::usage)?;
+    Ok(Command::ArchiveClth(parse_id(id)?, status))
+}
+
+fn parse_price(value: &str) -> Result<Money, CliErr> {
+    Money::from_str(value).map_err(|msg| {
+        CliErr::usage(&format!("'{}' is not a valid price: {}", value, msg))
+    })
+}
+
+fn parse_sell(args: &[String]) -> Result<Command, CliErr> {
+    let (id, price, rest) = match args {
+        [id, price, rest @ ..] => (parse_id(id)?, parse_price(price)?, rest),
+        _ => return Err(CliErr::usage("'sell' expects a clothing id and a price.")),
+    };
+
+    let mut channel = None;
+    let mut date = None;
+    let mut iter = rest.iter();
+    while let Some(flag) = iter.next() {
+        let slot = match flag.as_str() {
+            "--channel" => &mut channel,
+            "--date" => &mut date,
+            _ => return Err(CliErr::usage(&format!("Unknown option '{}'.", flag))),
+        };
+        match iter.next() {
+            Some(value) => *slot = Some(value.clone()),
+            None => return Err(CliErr::usage(&format!("Missing value for '{}'.", flag))),
+        }
+    }
+
+    let date = match date {
+        Some(value) => NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map_err(|_| CliErr::usage("--date: Invalid date."))?,
+        None => Local::today().naive_local(),
+    };
+    Ok(Command::Sell(Sale { clth: id, date, price, channel }))
+}
+
+fn parse_donate(args: &[String]) -> Result<Command, CliErr> {
+    let mut ids = Vec::new();
+    let mut recipient = None;
+    let mut date = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let slot = match arg.as_str() {
+            "--to" => &mut recipient,
+            "--date" => &mut date,
+            _ => {
+                ids.push(parse_id(arg)?);
+                continue;
+            },
+        };
+        match iter.next() {
+            Some(value) => *slot = Some(value.clone()),
+            None => return Err(CliErr::usage(&format!("Missing value for '{}'.", arg))),
+        }
+    }
+
+    if ids.is_empty() {
+        return Err(CliErr::usage("'donate' expects some clothing id."));
+    }
+    let recipient = match recipient {
+        Some(name) if !name.trim().is_empty() => String::from(name.trim()),
+        _ => return Err(CliErr::usage("Missing '--to' option.")),
+    };
+    let date = match date {
+        Some(value) => NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map_err(|_| CliErr::usage("--date: Invalid date."))?,
+        None => Local::today().naive_local(),
+    };
+    Ok(Command::Donate { ids, recipient, date })
+}
+
+fn parse_donation(args: &[String]) -> Result<Command, CliErr> {
+    match args {
+        [sub] if sub == "list" => Ok(Command::ListDonations),
+        [sub, id] if sub == "receipt" => Ok(Command::Receipt(parse_id(id)?, None)),
+        [sub, id, file] if sub == "receipt" => {
+            Ok(Command::Receipt(parse_id(id)?, Some(file.clone())))
+        },
+        _ => Err(CliErr::usage("Expected 'donation list' or 'donation receipt'.")),
+    }
+}
+
+fn parse_quarantine(args: &[String]) -> Result<Command, CliErr> {
+    let index = |value: &str| value.parse().map_err(|_| {
+        CliErr::usage(&format!("'{}' is not a valid record number.", value))
+    });
+
+    match args {
+        [sub] if sub == "list" => Ok(Command::ListQuarantine),
+        [sub, n] if sub == "retry" => Ok(Command::RetryQuarantined(index(n)?)),
+        [sub, n] if sub == "discard" => Ok(Command::DiscardQuarantined(index(n)?)),
+        _ => Err(CliErr::usage(
+            "Expected 'quarantine list', 'quarantine retry' or 'quarantine discard'.")),
+    }
+}
+
+pub struct Opts {
+    pub data: Option<String>,
+    pub store: Option<Backend>,
+    /// Quarantine invalid records instead of refusing the data file.
+    pub lenient: bool,
+    pub read_only: bool,
+    pub money: Option<money::Style>,
+    pub command: Option<Command>,
+}
+
+/// Splits the global options from the command, then parses the command (if
+/// any). `args` must not contain the program name.
+pub fn parse_opts(args: &[String]) -> Result<Opts, CliErr> {
+    let mut data = None;
+    let mut store = None;
+    let mut lenient = false;
+    let mut read_only = false;
+    let mut money = None;
+    let mut rest = args;
+
+    while let Some((flag, tail)) = rest.split_first() {
+        match flag.as_str() {
+            "--data" => match tail.split_first() {
+                Some((path, tail)) if !path.is_empty() => {
+                    data = Some(path.clone());
+                    rest = tail;
+                },
+                _ => return Err(CliErr::usage("Missing value for '--data'.")),
+            },
+            "--store" => match tail.split_first() {
+                Some((value, tail)) => {
+                    store = Some(parse_field::<Backend>("store", Some(value.clone()))?);
+                    rest = tail;
+                },
+                None => return Err(CliErr::usage("Missing value for '--store'.")),
+            },
+            "--money" => match tail.split_first() {
+                Some((value, tail)) => {
+                    money = Some(parse_field::<money::Style>("money", Some(value.clone()))?);
+                    rest = tail;
+                },
+                None => return Err(CliErr::usage("Missing value for '--money'.")),
+            },
+            "--lenient" => {
+                lenient = true;
+                rest = tail;
+            },
+            "--read-only" => {
+                read_only = true;
+                rest = tail;
+            },
+            _ => break,
+        }
+    }
+
+    let command = if rest.is_empty() { None } else { Some(parse(rest)?) };
+    Ok(Opts { data, store, lenient, read_only, money, command })
+}
+
+/// Parses the command line arguments (without the program name).
+pub fn parse(args: &[String]) -> Result<Command, CliErr> {
+    let (cmd, rest) = match args.split_first() {
+        Some(value) => value,
+        None => return Err(CliErr::usage("Missing command.")),
+    };
+
+    match (cmd.as_str(), rest) {
+        ("add-clth", rest) => parse_add_clth(rest),
+        ("list", []) => Ok(Command::ListClths),
+        ("kind", rest) => parse_kind(rest),
+        ("rm", [id]) => Ok(Command::RemoveClth(parse_id(id)?)),
+        ("cost", rest) => parse_set_cost(rest),
+        ("wear", [id]) => Ok(Command::WearClth(parse_id(id)?)),
+        ("archive", rest) => parse_archive(rest),
+        ("sell", rest) => parse_sell(rest),
+        ("sales", [sub]) if sub == "list" => Ok(Command::ListSales),
+        ("sales", [sub]) if sub == "report" => Ok(Command::SalesReport),
+        ("sales", [sub]) if sub == "costs" => Ok(Command::CostReport),
+        ("donate", rest) => parse_donate(rest),
+        ("donation", rest) => parse_donation(rest),
+        ("outfit", rest) => parse_outfit(rest),
+        ("export-json", []) => Ok(Command::ExportJson(None)),
+        ("export-json", [file]) => Ok(Command::ExportJson(Some(file.clone()))),
+        ("import-json", [file]) => Ok(Command::ImportJson(file.clone())),
+        ("export-csv", []) => Ok(Command::ExportCsv(None)),
+        ("export-csv", [file]) => Ok(Command::ExportCsv(Some(file.clone()))),
+        ("import-csv", [file]) => Ok(Command::ImportCsv(file.clone())),
+        ("quarantine", rest) => parse_quarantine(rest),
+        ("help" | "--help" | "-h", []) => Ok(Command::Help),
+        (cmd, _) => Err(CliErr::usage(&format!("Invalid use of '{}'.", cmd))),
+    }
+}
+
+fn read_input(file: &str) -> Result<String, CliErr> {
+    fs::read_to_string(file).map_err(|err| {
+        CliErr::rejected(&format!("Can't read {}: {}", file, err))
+    })
+}
+
+/// Writes `text` to `file`, or to stdout when no file is given.
+fn write_output(file: Option<&str>, text: &str) -> Result<(), CliErr> {
+    match file {
+        Some(file) => fs::write(file, format!("{}\n", text)).map_err(|err| {
+            CliErr::rejected(&format!("Can't write {}: {}", file, err))
+        }),
+        None => {
+            println!("{}", text);
+            Ok(())
+        },
+    }
+}
+
+/// Runs `cmd` against `data`, printing its output to stdout.
+pub fn exec(cmd: Command, data: &mut Data) -> Result<(), CliErr> {
+    match cmd {
+        Command::AddClth { mut buffer, kind, style, date, cost } => {
+            let kind = data.kinds.parse(&kind).map_err(|msg| {
+                CliErr::rejected(&format!("--kind: {}", msg))
+            })?;
+            if !buffer.size.as_ref().is_some_and(|size| kind.sizes.fits(size)) {
+                return Err(CliErr::rejected("--size: The size doesn't fit the kind."));
+            }
+            buffer.kind = Some(kind);
+            let id = crate::add_clth(data, buffer, &style, date, cost);
+            println!("{}", id);
+        },
+        Command::ListClths => println!("{}", &data.clothes.active()),
+        Command::AddKind(kind) => crate::add_kind(data, kind).map_err(CliErr::rejected)?,
+        Command::ListKinds => println!("{}", &data.kinds),
+        Command::RemoveClth(id) => {
+            crate::rm_clth(data, id).map_err(CliErr::rejected)?;
+        },
+        Command::SetCost(id, cost) => crate::set_cost(data, id, cost).map_err(CliErr::rejected)?,
+        Command::WearClth(id) => crate::wear_clth(data, id).map_err(CliErr::rejected)?,
+        Command::ArchiveClth(id, status) => {
+            crate::archive_clth(data, id, status).map_err(CliErr::rejected)?;
+        },
+        Command::ListArchived => println!("{}", &data.clothes.archived()),
+        Command::Sell(sale) => crate::sell_clth(data, sale).map_err(CliErr::rejected)?,
+        Command::ListSales => println!("{}", &data.sales),
+        Command::SalesReport => println!("{}", Report::new(data)),
+        Command::CostReport => println!("{}", costs::Report::new(data)),
+        Command::Donate { ids, recipient, date } => {
+            let id = crate::donate(data, &recipient, date, &ids).map_err(CliErr::rejected)?;
+            println!("{}", id);
+        },
+        Command::ListDonations => println!("{}", &data.donations),
+        Command::Receipt(id, file) => {
+            let donation = data.donations.get(id).ok_or_else(|| {
+                CliErr::rejected(&format!("Donation {} not found.", id))
+            })?;
+            write_output(file.as_deref(), &donation.receipt(&data.clothes))?;
+        },
+        Command::AddOutfit(ids) => {
+            let mut buffer = OutfitBuffer::new();
+            for id in ids {
+                match data.clothes.get(id) {
+                    Some(clth) => buffer.clothes.push(Rc::downgrade(clth)),
+                    None => return Err(CliErr::rejected(
+                        &format!("Clothing {} not found.", id))),
+                }
+            }
+            let id = crate::add_outfit(data, buffer).map_err(CliErr::rejected)?;
+            println!("{}", id);
+        },
+        Command::RemoveOutfit(id) => {
+            crate::rm_outfit(data, id).map_err(CliErr::rejected)?;
+        },
+        Command::ListOutfits => println!("{}", &data.outfits),
+        Command::ExportJson(file) => write_output(file.as_deref(), &json::export(data))?,
+        Command::ImportJson(file) => {
+            let text = read_input(&file)?;
+            *data = json::import(&text).map_err(|msg| {
+                CliErr::rejected(&format!("{}: {}", file, msg))
+            })?;
+            data.changes.push(Change::All);
+        },
+        Command::ExportCsv(file) => {
+            let text = csv::export(&data.clothes.active());
+            write_output(file.as_deref(), text.trim_end())?;
+        },
+        Command::ImportCsv(file) => {
+            let text = read_input(&file)?;
+            match csv::import(&text, data) {
+                Ok(ids) => ids.iter().for_each(|id| println!("{}", id)),
+                Err(errors) => {
+                    let lines: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+                    return Err(CliErr::rejected(
+                        &format!("{}:\n  {}", file, lines.join("\n  "))));
+                },
+            }
+        },
+        Command::ListQuarantine => println!("{}", &data.quarantine),
+        Command::RetryQuarantined(index) => {
+            quarantine::restore(data, index).map_err(|reasons| {
+                CliErr::rejected(&format!("Record {} is still invalid:\n  {}",
+                    index, reasons.join("\n  ")))
+            })?;
+        },
+        Command::DiscardQuarantined(index) => {
+            quarantine::discard(data, index).map_err(|msg| CliErr::rejected(&msg))?;
+        },
+        Command::Help => println!("{}", USAGE),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn add_clth(data: &mut Data, kind: &str) {
+        let line = format!("add-clth --kind {} --sex male --size m --color #FF00EE \
+            --target keep --style summer", kind);
+        exec(parse(&args(&line)).unwrap(), data).unwrap();
+    }
+
+    #[test]
+    fn parse_add_clth() {
+        let line = "add-clth --kind chest --size M --color #FF00EE --sex male \
+            --target sale --price 20.75 --style summer --date 2022-08-15";
+        match parse(&args(line)).unwrap() {
+            Command::AddClth { buffer, kind, style, date, cost } => {
+                assert_eq!("chest", kind);
+                assert!(cost.is_none());
+                assert_eq!(Some(Target::Sale(Money::usd(2075))), buffer.target);
+                assert_eq!("summer", style);
+                assert_eq!("2022-08-15", date.to_string());
+            },
+            _ => panic!("Expected an 'add-clth' command."),
+        }
+    }
+
+    #[test]
+    fn reject_bad_usage() {
+        let bad = [
+            "",
+            "unknown",
+            "rm",
+            "rm x",
+            "outfit add",
+            "outfit add 0 x",
+            "add-clth --kind hat",
+            "add-clth --kind chest --sex male --size m --color #FF00EE \
+                --target sale --style summer",
+        ];
+        for line in bad {
+            let err = parse(&args(line)).err().expect(line);
+            assert_eq!(EXIT_USAGE, err.code);
+        }
+    }
+
+    #[test]
+    fn parse_global_opts() {
+        let opts = parse_opts(&args("--data /tmp/closet.toml list")).unwrap();
+        assert_eq!(Some("/tmp/closet.toml"), opts.data.as_deref());
+        assert!(matches!(opts.command, Some(Command::ListClths)));
+
+        let opts = parse_opts(&args("")).unwrap();
+        assert!(opts.data.is_none() && opts.command.is_none());
+
+        assert!(parse_opts(&args("--data")).is_err());
+
+        let opts = parse_opts(&args("--lenient --data x.toml quarantine retry 2")).unwrap();
+        assert!(opts.lenient && !opts.read_only);
+        assert!(matches!(opts.command, Some(Command::RetryQuarantined(2))));
+        assert!(parse_opts(&args("quarantine retry x")).is_err());
+
+        let opts = parse_opts(&args("--store sqlite")).unwrap();
+        assert_eq!(Some(Backend::Sqlite), opts.store);
+        assert!(parse_opts(&args("--store csv list")).is_err());
+
+        let opts = parse_opts(&args("--money code sales report")).unwrap();
+        assert_eq!(Some(money::Style::Code), opts.money);
+        assert!(parse_opts(&args("--money euro list")).is_err());
+    }
+
+    #[test]
+    fn tell_mutating_commands() {
+        for line in ["list", "kind list", "sales costs", "donation receipt 0", "export-json",
+                "quarantine list", "help"] {
+            assert!(!parse(&args(line)).unwrap().mutates(), "{}", line);
+        }
+        for line in ["rm 0", "wear 0", "cost 0 none", "sell 0 10", "outfit rm 0",
+                "import-json x.json", "quarantine retry 0", "kind add Scarf accessory"] {
+            assert!(parse(&args(line)).unwrap().mutates(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn drive_closet() {
+        let mut data = Data::new();
+        add_clth(&mut data, "chest");
+        add_clth(&mut data, "leg");
+        add_clth(&mut data, "foot");
+
+        exec(parse(&args("outfit add 0 1 2")).unwrap(), &mut data).unwrap();
+        assert_eq!(vec![ vec![0, 1, 2] ], data.outfits.to_id_matrix());
+
+        let dup = exec(parse(&args("outfit add 0 1 2")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, dup.unwrap_err().code);
+
+        exec(parse(&args("rm 1")).unwrap(), &mut data).unwrap();
+        assert!(data.clothes.get(1).is_none());
+        assert!(data.outfits.list.is_empty());
+
+        let missing = exec(parse(&args("rm 1")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, missing.unwrap_err().code);
+    }
+
+    #[test]
+    fn archive_clothes() {
+        let mut data = Data::new();
+        add_clth(&mut data, "chest");
+        add_clth(&mut data, "leg");
+        add_clth(&mut data, "foot");
+        exec(parse(&args("outfit add 0 1 2")).unwrap(), &mut data).unwrap();
+
+        for line in ["archive 1 active", "archive 1 sold", "archive 1 donated 2023-01-02",
+                "archive 1 archived 2023-13-01"] {
+            assert_eq!(EXIT_USAGE, parse(&args(line)).err().expect(line).code);
+        }
+        exec(parse(&args("archive 1 archived 2023-01-02")).unwrap(), &mut data).unwrap();
+        let status = data.clothes.get(1).unwrap().borrow().status.to_string();
+        assert_eq!("Archived on 2023-01-02", status);
+        assert!(data.outfits.list.is_empty());
+        assert_eq!(1, data.clothes.archived().list.len());
+
+        let again = exec(parse(&args("archive 1 archived")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, again.unwrap_err().code);
+        let outfit = exec(parse(&args("outfit add 0 1 2")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, outfit.unwrap_err().code);
+    }
+
+    #[test]
+    fn donate_clothes() {
+        let mut data = Data::new();
+        let line = "add-clth --kind chest --sex male --size m --color #FF00EE \
+            --target donation --style summer";
+        exec(parse(&args(line)).unwrap(), &mut data).unwrap();
+        add_clth(&mut data, "leg");
+
+        assert!(parse(&args("donate 0")).is_err());
+        assert!(parse(&args("donate --to shelter")).is_err());
+        let kept = exec(parse(&args("donate 0 1 --to shelter")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, kept.unwrap_err().code);
+        assert!(data.donations.list.is_empty());
+
+        exec(parse(&args("donate 0 --to shelter --date 2023-02-01")).unwrap(), &mut data)
+            .unwrap();
+        assert_eq!(vec![0], data.donations.get(0).unwrap().clothes);
+        assert!(exec(parse(&args("donation receipt 0")).unwrap(), &mut data).is_ok());
+        let missing = exec(parse(&args("donation receipt 1")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, missing.unwrap_err().code);
+    }
+
+    #[test]
+    fn record_sales() {
+        let mut data = Data::new();
+        add_clth(&mut data, "chest");
+
+        assert!(parse(&args("sell 0")).is_err());
+        assert!(parse(&args("sell 0 -3")).err().unwrap().msg.ends_with("can't be negative."));
+        assert!(parse(&args("sell 0 19.999")).is_err());
+        assert!(parse(&args("sell 0 10 --channel")).is_err());
+        match parse(&args("sell 0 €5,50")).unwrap() {
+            Command::Sell(sale) => assert_eq!("5.50 EUR", sale.price.to_code()),
+            _ => panic!("Expected a 'sell' command."),
+        }
+        let line = "sell 0 $12.30 --channel market --date 2023-01-02";
+        exec(parse(&args(line)).unwrap(), &mut data).unwrap();
+        let sale = data.sales.get(0).unwrap();
+        assert_eq!((&Money::usd(1230), Some("market")), (&sale.price, sale.channel.as_deref()));
+        assert!(matches!(data.clothes.get(0).unwrap().borrow().status, Status::Sold(_)));
+
+        let again = exec(parse(&args("sell 0 5")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, again.unwrap_err().code);
+    }
+
+    #[test]
+    fn track_costs() {
+        let mut data = Data::new();
+        let line = "add-clth --kind chest --sex male --size m --color #FF00EE \
+            --target keep --style summer --cost 30 --currency eur";
+        exec(parse(&args(line)).unwrap(), &mut data).unwrap();
+        // the cost is part of the addition, undone along with it.
+        assert_eq!(1, data.history.done.len());
+        add_clth(&mut data, "leg");
+        assert_eq!("30.00 EUR", data.clothes.get(0).unwrap().borrow().cost.as_ref().unwrap()
+            .to_code());
+
+        assert!(parse(&args("cost 1 10 dollars")).is_err());
+        assert!(parse(&args(&line.replace("--cost 30 ", ""))).is_err());
+        exec(parse(&args("cost 1 $12.50")).unwrap(), &mut data).unwrap();
+        assert_eq!(Some(Money::usd(1250)), data.clothes.get(1).unwrap().borrow().cost);
+        exec(parse(&args("wear 1")).unwrap(), &mut data).unwrap();
+        exec(parse(&args("wear 1")).unwrap(), &mut data).unwrap();
+        assert_eq!(2, data.clothes.get(1).unwrap().borrow().wears);
+
+        exec(parse(&args("cost 0 none")).unwrap(), &mut data).unwrap();
+        assert!(data.clothes.get(0).unwrap().borrow().cost.is_none());
+        let missing = exec(parse(&args("wear 5")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, missing.unwrap_err().code);
+    }
+
+    #[test]
+    fn define_kinds() {
+        let mut data = Data::new();
+        let hat = exec(parse(&args("add-clth --kind hat --sex male --size m --color #FF00EE \
+            --target keep --style summer")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, hat.unwrap_err().code);
+
+        exec(parse(&args("kind add Hat accessory")).unwrap(), &mut data).unwrap();
+        add_clth(&mut data, "hat");
+        assert_eq!(Slot::Accessory, data.clothes.get(0).unwrap().borrow().kind.slot);
+
+        let again = exec(parse(&args("kind add HAT top")).unwrap(), &mut data);
+        assert_eq!(EXIT_REJECTED, again.unwrap_err().code);
+        for line in ["kind add Hat head", "kind add Hat", "kind", "kind add Hat top cm",
+            "kind add Hat top letter more"]
+        {
+            assert_eq!(EXIT_USAGE, parse(&args(line)).err().expect(line).code);
+        }
+    }
+
+    #[test]
+    fn size_by_kind() {
+        let mut data = Data::new();
+        exec(parse(&args("kind add Dress full-body numeric")).unwrap(), &mut data).unwrap();
+        assert_eq!(SizeSystem::Numeric, data.kinds.parse("dress").unwrap().sizes);
+
+        let sized = |kind: &str, size: &str| format!("add-clth --kind {} --sex female \
+            --size {} --color #FF00EE --target keep --style summer", kind, size);
+        for (kind, size) in [("dress", "38"), ("foot", "9.5us"), ("leg", "32x34"), ("foot", "l")] {
+            exec(parse(&args(&sized(kind, size))).unwrap(), &mut data).unwrap();
+        }
+        let sizes: Vec<String> = data.clothes.list
+            .iter()
+            .map(|clth| clth.borrow().size.to_string())
+            .collect();
+        assert_eq!(vec!["38 EU", "9.5 US", "32x34", "L"], sizes);
+
+        for (kind, size) in [("chest", "42"), ("dress", "32x34"), ("foot", "8y")] {
+            let clth = exec(parse(&args(&sized(kind, size))).unwrap(), &mut data);
+            assert_eq!(EXIT_REJECTED, clth.unwrap_err().code);
+        }
+        assert_eq!(EXIT_USAGE, parse(&args(&sized("chest", "huge"))).err().unwrap().code);
+    }
+
+    #[test]
+    fn build_any_outfit() {
+        let mut data = Data::new();
+        for line in ["kind add Dress full-body", "kind add Jacket outerwear",
+            "kind add Scarf accessory"]
+        {
+            exec(parse(&args(line)).unwrap(), &mut data).unwrap();
+        }
+        for kind in ["dress", "foot", "jacket", "jacket", "scarf", "chest"] {
+            add_clth(&mut data, kind);
+        }
+
+        exec(parse(&args("outfit add 4 1 0 2")).unwrap(), &mut data).unwrap();
+        assert_eq!(vec![ vec![0, 2, 1, 4] ], data.outfits.to_id_matrix());
+        for line in ["outfit add 0 1 2 3", "outfit add 0 1 5", "outfit add 0 4"] {
+            let outfit = exec(parse(&args(line)).unwrap(), &mut data);
+            assert_eq!(EXIT_REJECTED, outfit.unwrap_err().code);
+        }
+    }
+}
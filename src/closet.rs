@@ -1,26 +1,37 @@
 use chrono::NaiveDate;
 use std::fmt;
+use std::str::FromStr;
 use std::rc::{Rc, Weak};
 use std::cell::RefCell;
 use std::collections::HashMap;
 
+use crate::money::Money;
+use crate::sizes::{ Size, SizeSystem };
+
 pub type ErrMsg = &'static str;
 
+#[derive(Clone)]
 pub struct Clth {
     pub id: u32,
-    pub kind: Kind,
+    pub kind: Rc<Kind>,
     pub sex: Sex,
     pub size: Size,
     pub color: Rgb,
     pub target: Target,
     pub purchase_date: NaiveDate,
     pub style: Rc<Style>,
+    pub status: Status,
+    /// What the clothing was bought for, if known.
+    pub cost: Option<Money>,
+    /// Times the clothing has been worn.
+    pub wears: u32,
 }
 
 impl Clth {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         id: u32,
-        kind: Kind,
+        kind: Rc<Kind>,
         sex: Sex,
         size: Size,
         color: Rgb,
@@ -37,26 +48,38 @@ impl Clth {
             target,
             purchase_date,
             style,
+            status: Status::Active,
+            cost: None,
+            wears: 0,
         }
     }
 
-    pub fn to_toml(&self) -> String {
-        let mut result = String::from("[clth]\n");
-        result.push_str(&format!("id = {}\n", self.id));
-        result.push_str(&format!("kind = \"{}\"\n", self.kind));
-        result.push_str(&format!("sex = \"{}\"\n", self.sex));
-        result.push_str(&format!("size = \"{}\"\n", self.size));
-        result.push_str(&format!("color = \"{}\"\n", self.color.to_hex()));
-        result.push_str(&format!("target = \"{}\"\n", self.target));
-        result.push_str(&format!("purchase_date = \"{}\"\n", self.purchase_date));
-        result.push_str(&format!("style = \"{}\"", self.style.name));
-        result
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("id"), toml::Value::from(self.id));
+        table.insert(String::from("kind"), toml::Value::from(self.kind.to_string()));
+        table.insert(String::from("sex"), toml::Value::from(self.sex.to_string()));
+        table.insert(String::from("size"), toml::Value::from(self.size.to_string()));
+        table.insert(String::from("color"), toml::Value::from(self.color.to_hex()));
+        table.insert(String::from("target"), toml::Value::from(self.target.encode()));
+        table.insert(String::from("purchase_date"),
+            toml::Value::from(self.purchase_date.to_string()));
+        table.insert(String::from("style"), toml::Value::from(self.style.name.as_str()));
+        table.insert(String::from("status"), toml::Value::from(self.status.to_string()));
+        if let Some(cost) = &self.cost {
+            table.insert(String::from("purchase_price"), toml::Value::from(cost.stored_cents()));
+            table.insert(String::from("currency"), toml::Value::from(cost.currency.code()));
+        }
+        if self.wears > 0 {
+            table.insert(String::from("wears"), toml::Value::from(self.wears));
+        }
+        toml::Value::Table(table)
     }
 }
 
 impl fmt::Display for Clth {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut fields = Vec::with_capacity(8);
+        let mut fields = Vec::with_capacity(11);
         fields.push(format!("Id: {}", self.id));
         fields.push(format!("Kind: {}", self.kind));
         fields.push(format!("Sex: {}", self.sex));
@@ -65,32 +88,185 @@ impl fmt::Display for Clth {
         fields.push(format!("Target: {}", self.target));
         fields.push(format!("Purchase date: {}", self.purchase_date));
         fields.push(format!("Style: {}", &self.style.name));
+        fields.push(format!("Status: {}", self.status));
+        if let Some(cost) = &self.cost {
+            fields.push(format!("Purchase price: {}", cost));
+        }
+        fields.push(format!("Wears: {}", self.wears));
         write!(f, "{}", fields.join("\n"))
     }
 }
 
+/// Part of the body a kind of clothing is worn on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Slot {
+    Top,
+    Bottom,
+    FullBody,
+    Outerwear,
+    Footwear,
+    Accessory,
+}
+
+impl Slot {
+    pub const ALL: [Slot; 6] = [
+        Slot::Top, Slot::Bottom, Slot::FullBody, Slot::Outerwear, Slot::Footwear,
+        Slot::Accessory,
+    ];
+}
+
+impl FromStr for Slot {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Slot, ErrMsg> {
+        let slot = match value.to_lowercase().as_str() {
+            "top" => Slot::Top,
+            "bottom" => Slot::Bottom,
+            "full-body" => Slot::FullBody,
+            "outerwear" => Slot::Outerwear,
+            "footwear" => Slot::Footwear,
+            "accessory" => Slot::Accessory,
+            _ => return Err("Invalid slot.")
+        };
+        Ok(slot)
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Slot::Top => "top",
+            Slot::Bottom => "bottom",
+            Slot::FullBody => "full-body",
+            Slot::Outerwear => "outerwear",
+            Slot::Footwear => "footwear",
+            Slot::Accessory => "accessory",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A kind of clothing, like `Chest` or `Dress`, worn on `slot` and sized
+/// in `sizes`.
 #[derive(Debug, Clone, PartialEq)]
-pub enum Kind {
-    Chest,
-    Leg,
-    Foot,
+pub struct Kind {
+    pub name: String,
+    pub slot: Slot,
+    pub sizes: SizeSystem,
 }
 
 impl Kind {
-    pub fn from_str(value: &str) -> Result<Kind, ErrMsg> {
-        let kind = match value.to_lowercase().as_str() {
-            "chest" => Kind::Chest,
-            "leg" => Kind::Leg,
-            "foot" => Kind::Foot,
-            _ => return Err("Invalid kind.")
-        };
-        Ok(kind)
+    /// Creates a kind sized in the usual system of `slot`.
+    pub fn new(name: &str, slot: Slot) -> Kind {
+        Kind { name: String::from(name), slot, sizes: SizeSystem::of_slot(slot) }
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("name"), toml::Value::from(self.name.as_str()));
+        table.insert(String::from("slot"), toml::Value::from(self.slot.to_string()));
+        // the size system is only written when it isn't the slot's one.
+        if self.sizes != SizeSystem::of_slot(self.slot) {
+            table.insert(String::from("sizes"), toml::Value::from(self.sizes.to_string()));
+        }
+        toml::Value::Table(table)
     }
 }
 
 impl fmt::Display for Kind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The kinds every closet has.
+const BUILTIN_KINDS: [(&str, Slot); 3] = [
+    ("Chest", Slot::Top),
+    ("Leg", Slot::Bottom),
+    ("Foot", Slot::Footwear),
+];
+
+/// The kinds of clothing of a closet: the built-in ones followed by the
+/// ones defined by the user, which are the only ones stored. Names are
+/// matched case-insensitively.
+pub struct Kinds {
+    list: Vec<Rc<Kind>>,
+}
+
+impl Default for Kinds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Kinds {
+    pub fn new() -> Kinds {
+        let list = BUILTIN_KINDS
+            .iter()
+            .map(|(name, slot)| Rc::new(Kind::new(name, *slot)))
+            .collect();
+        Kinds { list }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rc<Kind>> {
+        let name = name.trim();
+        self.list.iter().find(|kind| kind.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the kind named `value`.
+    pub fn parse(&self, value: &str) -> Result<Rc<Kind>, ErrMsg> {
+        self.get(value).map(Rc::clone).ok_or("Invalid kind.")
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.list.iter().map(|kind| kind.name.as_str()).collect()
+    }
+
+    pub fn list(&self) -> &[Rc<Kind>] {
+        &self.list
+    }
+
+    /// The kinds defined by the user.
+    pub fn custom(&self) -> &[Rc<Kind>] {
+        &self.list[BUILTIN_KINDS.len()..]
+    }
+
+    pub fn add(&mut self, kind: Kind) -> Result<Rc<Kind>, ErrMsg> {
+        let name = kind.name.trim();
+        if name.is_empty() {
+            return Err("A kind needs a name.");
+        }
+        if self.get(name).is_some() {
+            return Err("A kind with the same name already exists.");
+        }
+        self.list.push(Rc::new(Kind { name: String::from(name), ..kind }));
+        Ok(Rc::clone(self.list.last().unwrap()))
+    }
+
+    /// Removes the user-defined kind `name`.
+    pub fn remove(&mut self, name: &str) -> Result<Rc<Kind>, ErrMsg> {
+        let index = self.list
+            .iter()
+            .position(|kind| kind.name.eq_ignore_ascii_case(name.trim()))
+            .ok_or("Kind not found.")?;
+        if index < BUILTIN_KINDS.len() {
+            return Err("Built-in kinds can't be removed.");
+        }
+        Ok(self.list.remove(index))
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(self.custom().iter().map(|kind| kind.to_toml()).collect())
+    }
+}
+
+impl fmt::Display for Kinds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kinds: Vec<String> = self.list
+            .iter()
+            .map(|kind| format!("{} ({}, {} sizes)", kind.name, kind.slot, kind.sizes))
+            .collect();
+        write!(f, "{}", kinds.join("\n"))
     }
 }
 
@@ -101,8 +277,10 @@ pub enum Sex {
     Unissex,
 }
 
-impl Sex {
-    pub fn from_str(value: &str) -> Result<Sex, ErrMsg> {
+impl FromStr for Sex {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Sex, ErrMsg> {
         let sex = match value.to_lowercase().as_str() {
             "male" => Sex::Male,
             "female" => Sex::Female,
@@ -119,35 +297,6 @@ impl fmt::Display for Sex {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Size {
-    XS,
-    S,
-    M,
-    L,
-    XL,
-}
-
-impl Size {
-    pub fn from_str(value: &str) -> Result<Size, ErrMsg> {
-        let size = match value.to_lowercase().as_str() {
-            "xs" => Size::XS,
-            "s" => Size::S,
-            "m" => Size::M,
-            "l" => Size::L,
-            "xl" => Size::XL,
-            _ => return Err("Invalid size.")
-        };
-        Ok(size)
-    }
-}
-
-impl fmt::Display for Size {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 pub struct Rgb(pub u8, pub u8, pub u8);
 
@@ -185,25 +334,35 @@ impl fmt::Display for Rgb {
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Target {
-    Sale(u64),
+    /// On sale for its asking price.
+    Sale(Money),
     Donation,
     Keep,
 }
 
 impl Target {
-    pub fn from_str(value: &str) -> Result<Target, ErrMsg> {
-        let value = value.to_lowercase();
-        let target = match value.split_once('$') {
-            Some(("sale for ", price)) => {
-                let price: f64 = match price.parse(){
-                    Ok(num) => num,
-                    Err(_) => return Err("Invalid price."),
-                };
-                let cents = (price * 100.0).trunc() as u64;
-                Target::Sale(cents)
+    /// Formats the target the way it is stored, with the asking price as
+    /// `Money::to_code`.
+    pub fn encode(&self) -> String {
+        match self {
+            Target::Sale(price) => format!("Sale for {}", price.to_code()),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Target, ErrMsg> {
+        let prefix = "sale for ";
+        let target = match value.to_lowercase().as_str() {
+            "donation" => Target::Donation,
+            "keep" => Target::Keep,
+            lower if lower.starts_with(prefix) => {
+                // the price keeps its case, for symbols like `R$`.
+                Target::Sale(Money::from_str(&value[prefix.len()..])?)
             },
-            None if value == "donation" => Target::Donation,
-            None if value == "keep" => Target::Keep,
             _ => return Err("Invalid target.")
         };
         Ok(target)
@@ -213,13 +372,68 @@ impl Target {
 impl fmt::Display for Target {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Self::Sale(price) = self {
-            write!(f, "Sale for ${:.2}", *price as f64 / 100.0)
+            write!(f, "Sale for {}", price)
         } else {
             write!(f, "{:?}", self)
         }
     }
 }
 
+/// Where a clothing is in its lifecycle. Only active clothes are listed and
+/// used in outfits; the others are kept as a record of what became of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Active,
+    Archived(NaiveDate),
+    Sold(NaiveDate),
+    Donated(NaiveDate),
+}
+
+impl Status {
+    pub fn is_active(&self) -> bool {
+        matches!(self, Status::Active)
+    }
+
+    /// Returns the date the clothing left the closet, if it did.
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self {
+            Status::Active => None,
+            Status::Archived(date) | Status::Sold(date) | Status::Donated(date) => Some(*date),
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Status, ErrMsg> {
+        let value = value.to_lowercase();
+        if value == "active" {
+            return Ok(Status::Active);
+        }
+
+        let (status, date) = value.split_once(" on ").ok_or("Invalid status.")?;
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| "Invalid date.")?;
+        match status {
+            "archived" => Ok(Status::Archived(date)),
+            "sold" => Ok(Status::Sold(date)),
+            "donated" => Ok(Status::Donated(date)),
+            _ => Err("Invalid status."),
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Status::Active => write!(f, "Active"),
+            Status::Archived(date) => write!(f, "Archived on {}", date),
+            Status::Sold(date) => write!(f, "Sold on {}", date),
+            Status::Donated(date) => write!(f, "Donated on {}", date),
+        }
+    }
+}
+
 pub struct Style {
     pub name: String,
 }
@@ -236,6 +450,12 @@ pub struct Clothes {
     pub list: Vec<Rc<RefCell<Clth>>>,
 }
 
+impl Default for Clothes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Clothes {
     pub fn new() -> Clothes {
         Clothes { list: Vec::new() }
@@ -249,7 +469,7 @@ impl Clothes {
     }
 
     pub fn add(&mut self, clth: Clth) -> Option<ErrMsg> {
-        if let Some(_) = self.get(clth.id) {
+        if self.get(clth.id).is_some() {
             return Some("A clothing with the same id already exists in 'Clothes'");
         }
         self.list.push(Rc::new(RefCell::new(clth)));
@@ -257,18 +477,27 @@ impl Clothes {
     }
 
     pub fn remove(&mut self, id: u32) -> Result<Rc<RefCell<Clth>>, &'static str> {
-        let index = match self.list
-            .iter()
-            .map(|el| el.borrow().id)
-            .collect::<Vec<_>>()
-            .binary_search(&id) {
-                Ok(index) => index,
-                Err(_) => return Err("Clothing not found."),
-            };
+        let index = match self.list.iter().position(|el| el.borrow().id == id) {
+            Some(index) => index,
+            None => return Err("Clothing not found."),
+        };
 
         Ok(self.list.swap_remove(index))
     }
 
+    /// Archives the clothing `id` with `status` instead of removing it.
+    pub fn archive(&mut self, id: u32, status: Status) -> Result<(), ErrMsg> {
+        let clth = self.get(id).ok_or("Clothing not found.")?;
+        if status.is_active() {
+            return Err("Archived clothes need a status other than 'Active'.");
+        }
+        if !clth.borrow().status.is_active() {
+            return Err("The clothing is already archived.");
+        }
+        clth.borrow_mut().status = status;
+        Ok(())
+    }
+
     pub fn request_id(&self) -> u32 {
         let ids: Vec<u32> = self.list.iter().map(|clth| clth.borrow().id).collect();
         let mut new_id = 0;
@@ -279,12 +508,7 @@ impl Clothes {
     }
 
     pub fn get(&self, id: u32) -> Option<&Rc<RefCell<Clth>>> {
-        for clth in self.list.iter() {
-            if clth.borrow().id == id {
-                return Some(clth);
-            }
-        }
-        None
+        self.list.iter().find(|&clth| clth.borrow().id == id)
     }
 
     pub fn filter_by_color(&self, color: Rgb) -> Clothes {
@@ -313,6 +537,25 @@ impl Clothes {
         Clothes { list: filtered }
     }
 
+    /// Returns the clothes in use, leaving out the archived ones.
+    pub fn active(&self) -> Clothes {
+        let list = self.list
+            .iter()
+            .filter(|clth| clth.borrow().status.is_active())
+            .map(Rc::clone)
+            .collect();
+        Clothes { list }
+    }
+
+    pub fn archived(&self) -> Clothes {
+        let list = self.list
+            .iter()
+            .filter(|clth| !clth.borrow().status.is_active())
+            .map(Rc::clone)
+            .collect();
+        Clothes { list }
+    }
+
     pub fn map_by_target(&self) -> HashMap<&str, Clothes> {
         let mut keep = Vec::new();
         let mut donation = Vec::new();
@@ -333,32 +576,38 @@ impl Clothes {
         map
     }
 
-    pub fn map_by_kind(&self) -> HashMap<&str, Clothes> {
-        let mut chest = Vec::new();
-        let mut legs = Vec::new();
-        let mut foot = Vec::new();
+    /// Groups the clothes by the name of their kind, with a group for every
+    /// kind of `kinds`.
+    pub fn map_by_kind<'a>(&self, kinds: &'a Kinds) -> HashMap<&'a str, Clothes> {
+        let mut map: HashMap<&str, Clothes> = kinds
+            .names()
+            .into_iter()
+            .map(|name| (name, Clothes::new()))
+            .collect();
 
         for clth in self.list.iter() {
-            match clth.borrow().kind {
-                Kind::Chest => chest.push(Rc::clone(clth)),
-                Kind::Leg => legs.push(Rc::clone(clth)),
-                Kind::Foot => foot.push(Rc::clone(clth)),
+            let kind = kinds.get(&clth.borrow().kind.name);
+            if let Some(group) = kind.and_then(|kind| map.get_mut(kind.name.as_str())) {
+                group.list.push(Rc::clone(clth));
             }
         }
-
-        let mut map = HashMap::new();
-        map.insert("chest", Clothes { list: chest });
-        map.insert("leg", Clothes { list: legs });
-        map.insert("foot", Clothes { list: foot });
         map
     }
 
-    pub fn to_toml(&self) -> String {
-        self.list
+    /// Returns the clothes worn on any of `slots`.
+    pub fn of_slots(&self, slots: &[Slot]) -> Clothes {
+        let list = self.list
             .iter()
-            .map(|clth| clth.borrow().to_toml() )
-            .collect::<Vec<_>>()
-            .join("\n\n")
+            .filter(|clth| slots.contains(&clth.borrow().kind.slot))
+            .map(Rc::clone)
+            .collect();
+        Clothes { list }
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(
+            self.list.iter().map(|clth| clth.borrow().to_toml()).collect()
+        )
     }
 }
 
@@ -381,6 +630,12 @@ pub struct Styles {
     list: Vec<Rc<Style>>,
 }
 
+impl Default for Styles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Styles {
     pub fn new() -> Styles {
         Styles { list: Vec::new() }
@@ -391,12 +646,11 @@ impl Styles {
     }
 
     pub fn get(&self, name: &str) -> Option<&Rc<Style>> {
-        for style in self.list.iter() {
-            if &style.name == name {
-                return Some(style);
-            }
-        }
-        None
+        self.list.iter().find(|&style| style.name == name)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.list.iter().map(|style| style.name.as_str()).collect()
     }
 
     pub fn get_or_add(&mut self, name: &str) -> Rc<Style> {
@@ -409,74 +663,97 @@ impl Styles {
         };
         Rc::clone(stl)
     }
+
+    pub fn remove(&mut self, name: &str) {
+        self.list.retain(|style| style.name != name);
+    }
 }
 
+/// A set of clothes of the same style worn together: one footwear, either a
+/// top and a bottom or a full-body clothing, and optionally an outerwear and
+/// any accessories.
 pub struct Outfit {
     pub id: u32,
-    pub chest: Weak<RefCell<Clth>>,
-    pub leg: Weak<RefCell<Clth>>,
-    pub foot: Weak<RefCell<Clth>>,
+    /// Each clothing along with the slot it is worn on, ordered by slot and
+    /// then by id.
+    pub clothes: Vec<(Slot, Weak<RefCell<Clth>>)>,
 }
 
-impl Outfit {
-    pub fn new(
-        id: u32,
-        chest: Weak<RefCell<Clth>>,
-        leg: Weak<RefCell<Clth>>,
-        foot: Weak<RefCell<Clth>>,
-    ) -> Result<Outfit, &'static str> {
-        let up = chest.upgrade().unwrap();
-        let up_stl = &up.borrow().style.name;
-        let up_kind = &up.borrow().kind;
-
-        let low = leg.upgrade().unwrap();
-        let low_stl = &low.borrow().style.name;
-        let low_kind = &low.borrow().kind;
+/// Checks that `slots` make a whole outfit.
+fn check_slots(slots: &[Slot]) -> Result<(), ErrMsg> {
+    let count = |slot: Slot| slots.iter().filter(|&&other| other == slot).count();
 
-        let ft = foot.upgrade().unwrap();
-        let foot_stl = &ft.borrow().style.name;
-        let foot_kind = &ft.borrow().kind;
+    if count(Slot::Footwear) != 1 {
+        return Err("An outfit needs exactly one footwear.");
+    }
+    match (count(Slot::Top), count(Slot::Bottom), count(Slot::FullBody)) {
+        (1, 1, 0) | (0, 0, 1) => (),
+        _ => return Err("An outfit needs either a top and a bottom or a full-body clothing."),
+    }
+    if count(Slot::Outerwear) > 1 {
+        return Err("An outfit has at most one outerwear.");
+    }
+    Ok(())
+}
 
-        if !(up_stl == low_stl && low_stl == foot_stl) {
-            return Err("The clothes of a clothing set must have the same style.");
+impl Outfit {
+    pub fn new(id: u32, clothes: Vec<Weak<RefCell<Clth>>>) -> Result<Outfit, ErrMsg> {
+        let clothes = clothes
+            .iter()
+            .map(Weak::upgrade)
+            .collect::<Option<Vec<_>>>()
+            .ok_or("The clothes of the outfit are gone.")?;
+
+        let mut ids: Vec<u32> = clothes.iter().map(|clth| clth.borrow().id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.len() != clothes.len() {
+            return Err("A clothing can only be once in an outfit.");
         }
 
-        if let (Kind::Chest, Kind::Leg, Kind::Foot) = (&up_kind, &low_kind, &foot_kind) {
-            Ok(Outfit { id, chest, leg, foot })
-        } else {
-            Err("Invalid clothing set!")
+        if let Some((first, rest)) = clothes.split_first() {
+            let style = &first.borrow().style.name;
+            if rest.iter().any(|clth| &clth.borrow().style.name != style) {
+                return Err("The clothes of a clothing set must have the same style.");
+            }
         }
+
+        let mut clothes: Vec<(Slot, u32, Weak<RefCell<Clth>>)> = clothes
+            .iter()
+            .map(|clth| (clth.borrow().kind.slot, clth.borrow().id, Rc::downgrade(clth)))
+            .collect();
+        check_slots(&clothes.iter().map(|(slot, _, _)| *slot).collect::<Vec<_>>())?;
+
+        clothes.sort_by_key(|(slot, id, _)| (*slot, *id));
+        let clothes = clothes.into_iter().map(|(slot, _, clth)| (slot, clth)).collect();
+        Ok(Outfit { id, clothes })
     }
 
     pub fn to_clothes(&self) -> Clothes {
-        let list = vec![
-            self.chest.upgrade().unwrap(),
-            self.leg.upgrade().unwrap(),
-            self.foot.upgrade().unwrap(),
-        ];
+        let list = self.clothes
+            .iter()
+            .map(|(_, clth)| clth.upgrade().unwrap())
+            .collect();
         Clothes { list }
     }
 
-    pub fn to_id_arr(&self) -> [u32; 3] {
-        [
-            self.chest.upgrade().unwrap().borrow().id,
-            self.leg.upgrade().unwrap().borrow().id,
-            self.foot.upgrade().unwrap().borrow().id,
-        ]
+    /// Returns the ids of the clothes, in the order of `clothes`.
+    pub fn to_ids(&self) -> Vec<u32> {
+        self.clothes
+            .iter()
+            .map(|(_, clth)| clth.upgrade().unwrap().borrow().id)
+            .collect()
     }
 
     pub fn is_valid(&self) -> bool {
-        self.chest.upgrade().is_some() && self.leg.upgrade().is_some() &&
-            self.foot.upgrade().is_some()
+        self.clothes.iter().all(|(_, clth)| clth.upgrade().is_some())
     }
 
-    pub fn to_toml(&self) -> String {
-        let [chest, leg, foot] = self.to_id_arr();
-        let mut result = String::from("[outfit]\n");
-        result.push_str(&format!("chest = {}\n", chest));
-        result.push_str(&format!("leg = {}\n", leg));
-        result.push_str(&format!("foot = {}", foot));
-        result
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("id"), toml::Value::from(self.id));
+        table.insert(String::from("clothes"), toml::Value::from(self.to_ids()));
+        toml::Value::Table(table)
     }
 }
 
@@ -484,11 +761,11 @@ impl fmt::Display for Outfit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let title = format!("[ Outfit {} ]", self.id);
 
-        let body = [
-            self.chest.upgrade().unwrap().borrow().to_string(),
-            self.leg.upgrade().unwrap().borrow().to_string(),
-            self.foot.upgrade().unwrap().borrow().to_string(),
-        ].join("\n\n");
+        let body = self.clothes
+            .iter()
+            .map(|(slot, clth)| format!("Slot: {}\n{}", slot, clth.upgrade().unwrap().borrow()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
 
         write!(f, "{}\n{}", title, body)
     }
@@ -499,13 +776,24 @@ pub struct Outfits {
     pub list: Vec<Outfit>
 }
 
+impl Default for Outfits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Outfits {
     pub fn new() -> Outfits {
         Outfits { list: Vec::new() }
     }
 
+    /// Adds `outfit`, unless its id is taken or another outfit has the same
+    /// clothes.
     pub fn add(&mut self, outfit: Outfit) -> Option<ErrMsg> {
-        if self.to_id_matrix().contains(&outfit.to_id_arr()) {
+        if self.get(outfit.id).is_some() {
+            return Some("An outfit with the same id already exists in 'Outfits'");
+        }
+        if self.find(&outfit.to_ids()).is_some() {
             return Some("This outfit already exists!");
         }
         self.list.push(outfit);
@@ -513,32 +801,42 @@ impl Outfits {
     }
 
     pub fn remove(&mut self, id: u32) -> Result<Outfit, &'static str> {
-        let index = match self.list
-            .iter()
-            .map(|el| el.id)
-            .collect::<Vec<_>>()
-            .binary_search(&id) {
-                Ok(index) => index,
-                Err(_) => return Err("Clothing not found."),
-            };
+        let index = match self.list.iter().position(|el| el.id == id) {
+            Some(index) => index,
+            None => return Err("Outfit not found."),
+        };
 
         Ok(self.list.swap_remove(index))
     }
 
-    pub fn to_id_matrix(&self) -> Vec<[u32; 3]> {
+    pub fn to_id_matrix(&self) -> Vec<Vec<u32>> {
         self.list
             .iter()
-            .map(|set| set.to_id_arr())
+            .map(|set| set.to_ids())
             .collect::<Vec<_>>()
     }
 
+    /// Returns the outfit made of the clothes `ids`, in any order.
+    pub fn find(&self, ids: &[u32]) -> Option<&Outfit> {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        self.list.iter().find(|outfit| {
+            let mut other = outfit.to_ids();
+            other.sort_unstable();
+            other == ids
+        })
+    }
+
     pub fn get(&self, id: u32) -> Option<&Outfit> {
-        for outfit in self.list.iter() {
-            if outfit.id == id {
-                return Some(outfit);
-            }
-        }
-        None
+        self.list.iter().find(|&outfit| outfit.id == id)
+    }
+
+    /// Returns the outfits using the clothing `clth_id`.
+    pub fn using(&self, clth_id: u32) -> Vec<&Outfit> {
+        self.list
+            .iter()
+            .filter(|outfit| outfit.to_ids().contains(&clth_id))
+            .collect()
     }
 
     pub fn request_id(&self) -> u32 {
@@ -550,6 +848,12 @@ impl Outfits {
         new_id
     }
 
+    /// Removes the outfits using the clothing `clth_id`. They are found by
+    /// id, since the clothing may still be held elsewhere.
+    pub fn remove_using(&mut self, clth_id: u32) {
+        self.list.retain(|outfit| !outfit.to_ids().contains(&clth_id));
+    }
+
     pub fn clean(&mut self) {
         let black_list: Vec<_> = self.list
             .iter()
@@ -560,12 +864,8 @@ impl Outfits {
         black_list.iter().for_each(|id| { self.remove(*id).unwrap(); });
     }
     
-    pub fn to_toml(&self) -> String {
-        self.list
-            .iter()
-            .map(|item| item.to_toml())
-            .collect::<Vec<_>>()
-            .join("\n\n")
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(self.list.iter().map(|item| item.to_toml()).collect())
     }
 }
 
@@ -584,14 +884,20 @@ impl fmt::Display for Outfits {
 }
 
 pub struct ClthBuffer {
-    pub kind: Option<Kind>,
+    pub kind: Option<Rc<Kind>>,
     pub sex: Option<Sex>,
     pub size: Option<Size>,
     pub color: Option<Rgb>,
-    pub price: Option<u64>,
+    pub price: Option<Money>,
     pub target: Option<Target>,
 }
 
+impl Default for ClthBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ClthBuffer {
     pub fn new() -> ClthBuffer {
         ClthBuffer {
@@ -604,6 +910,34 @@ impl ClthBuffer {
         }
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.kind.is_none() && self.sex.is_none() && self.size.is_none() &&
+            self.color.is_none() && self.price.is_none() && self.target.is_none()
+    }
+
+    /// Builds a table with the fields filled so far.
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        let fields = [
+            ("kind", self.kind.as_ref().map(|kind| kind.to_string())),
+            ("sex", self.sex.as_ref().map(|sex| sex.to_string())),
+            ("size", self.size.as_ref().map(|size| size.to_string())),
+            ("color", self.color.as_ref().map(|color| color.to_hex())),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                table.insert(String::from(key), toml::Value::from(value));
+            }
+        }
+        if let Some(price) = &self.price {
+            table.insert(String::from("price"), toml::Value::from(price.to_code()));
+        }
+        if let Some(target) = &self.target {
+            table.insert(String::from("target"), toml::Value::from(target.encode()));
+        }
+        toml::Value::Table(table)
+    }
+
     pub fn to_clth(self, id: u32, date: NaiveDate, style: Rc<Style>) -> Clth {
         Clth::new(
             id,
@@ -619,36 +953,68 @@ impl ClthBuffer {
 }
 
 pub struct OutfitBuffer {
-    pub chest: Option<Weak<RefCell<Clth>>>,
-    pub leg: Option<Weak<RefCell<Clth>>>,
-    pub foot: Option<Weak<RefCell<Clth>>>
+    /// The clothes selected so far.
+    pub clothes: Vec<Weak<RefCell<Clth>>>,
+}
+
+impl Default for OutfitBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OutfitBuffer {
     pub fn new() -> OutfitBuffer {
-        OutfitBuffer {
-            chest: None,
-            leg: None,
-            foot: None
-        }
+        OutfitBuffer { clothes: Vec::new() }
     }
 
-    pub fn to_outfit(self, id: u32) -> Result<Outfit, ErrMsg> {
-        let chest = match self.chest {
-            Some(value) => value,
-            None => return Err("Missing 'chest' field on buffer.")
-        };
+    pub fn is_empty(&self) -> bool {
+        self.clothes.is_empty()
+    }
 
-        let leg = match self.leg {
-            Some(value) => value,
-            None => return Err("Missing 'leg' field on buffer.")
-        };
+    /// Returns the slots taken by the clothes selected so far.
+    pub fn slots(&self) -> Vec<Slot> {
+        self.clothes
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|clth| clth.borrow().kind.slot)
+            .collect()
+    }
 
-        let foot = match self.foot {
-            Some(value) => value,
-            None => return Err("Missing 'foot' field on buffer.")
+    /// Returns the ids of the clothes selected so far.
+    pub fn ids(&self) -> Vec<u32> {
+        self.clothes
+            .iter()
+            .filter_map(Weak::upgrade)
+            .map(|clth| clth.borrow().id)
+            .collect()
+    }
+
+    /// Returns the clothes of `clothes` that can still be added as extras:
+    /// accessories not selected yet, and an outerwear if there is none.
+    pub fn extras(&self, clothes: &Clothes) -> Clothes {
+        let slots = match self.slots().contains(&Slot::Outerwear) {
+            true => vec![Slot::Accessory],
+            false => vec![Slot::Outerwear, Slot::Accessory],
         };
-        Outfit::new(id, chest, leg, foot)
+        let ids = self.ids();
+        let list = clothes.of_slots(&slots).list
+            .into_iter()
+            .filter(|clth| !ids.contains(&clth.borrow().id))
+            .collect();
+        Clothes { list }
+    }
+
+    /// Builds a table with the ids of the clothes selected so far.
+    pub fn to_toml(&self) -> toml::Value {
+        let ids = self.ids();
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("clothes"), toml::Value::from(ids));
+        toml::Value::Table(table)
+    }
+
+    pub fn to_outfit(self, id: u32) -> Result<Outfit, ErrMsg> {
+        Outfit::new(id, self.clothes)
     }
 }
 
@@ -660,9 +1026,10 @@ mod tests {
 
     #[test]
     fn reject_invalid_clthset() {
+        let kinds = Kinds::new();
         let clth1 = Rc::new(RefCell::new(Clth::new(
             0,
-            Kind::Chest,
+            kinds.parse("chest").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -673,7 +1040,7 @@ mod tests {
 
         let clth2 = Rc::new(RefCell::new(Clth::new(
             0,
-            Kind::Leg,
+            kinds.parse("leg").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -684,7 +1051,7 @@ mod tests {
 
         let clth3 = Rc::new(RefCell::new(Clth::new(
             0,
-            Kind::Foot,
+            kinds.parse("foot").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -693,29 +1060,28 @@ mod tests {
             Rc::new(Style::new("style3")),
         )));
 
-        let set1 = Outfit::new(
-            0,
+        let set1 = Outfit::new(0, vec![
             Rc::downgrade(&clth1),
             Rc::downgrade(&clth1),
             Rc::downgrade(&clth1),
-        );
+        ]);
 
-        let set2 = Outfit::new(
-            1,
+        let set2 = Outfit::new(1, vec![
             Rc::downgrade(&clth1),
             Rc::downgrade(&clth2),
             Rc::downgrade(&clth3),
-        );
+        ]);
 
         assert!(set1.is_err() && set2.is_err());
     }
 
     #[test]
     fn accept_valid_clthset() {
+        let kinds = Kinds::new();
         let style = Rc::new(Style::new("style"));
         let clth1 = Rc::new(RefCell::new(Clth::new(
             0,
-            Kind::Chest,
+            kinds.parse("chest").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -725,8 +1091,8 @@ mod tests {
         )));
 
         let clth2 = Rc::new(RefCell::new(Clth::new(
-            0,
-            Kind::Leg,
+            1,
+            kinds.parse("leg").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -736,8 +1102,8 @@ mod tests {
         )));
 
         let clth3 = Rc::new(RefCell::new(Clth::new(
-            0,
-            Kind::Foot,
+            2,
+            kinds.parse("foot").unwrap(),
             Sex::Male,
             Size::M,
             Rgb(0, 0, 0),
@@ -746,14 +1112,71 @@ mod tests {
             style,
         )));
 
-        assert!(Outfit::new(
-                0,
+        assert!(Outfit::new(0, vec![
+                Rc::downgrade(&clth3),
                 Rc::downgrade(&clth1),
-                Rc::downgrade(&clth2),
-                Rc::downgrade(&clth3))
+                Rc::downgrade(&clth2)])
         .is_ok());
     }
 
+    #[test]
+    fn check_outfit_slots() {
+        let mut kinds = Kinds::new();
+        kinds.add(Kind::new("Dress", Slot::FullBody)).unwrap();
+        kinds.add(Kind::new("Coat", Slot::Outerwear)).unwrap();
+        kinds.add(Kind::new("Hat", Slot::Accessory)).unwrap();
+        let style = Rc::new(Style::new("style"));
+        let clothes: Vec<_> = ["chest", "leg", "foot", "dress", "coat", "coat", "hat"]
+            .iter()
+            .enumerate()
+            .map(|(id, kind)| Rc::new(RefCell::new(Clth::new(
+                id as u32,
+                kinds.parse(kind).unwrap(),
+                Sex::Female,
+                Size::M,
+                Rgb(0, 0, 0),
+                Target::Keep,
+                Local::today().naive_local(),
+                Rc::clone(&style),
+            ))))
+            .collect();
+        let outfit = |ids: &[usize]| Outfit::new(0, ids
+            .iter()
+            .map(|id| Rc::downgrade(&clothes[*id]))
+            .collect());
+
+        assert!(outfit(&[3, 2]).is_ok());
+        assert!(outfit(&[6, 4, 3, 2]).is_ok());
+        assert!(outfit(&[0, 1, 2, 4, 6]).is_ok());
+        assert!(outfit(&[3]).is_err());
+        assert!(outfit(&[0, 2]).is_err());
+        assert!(outfit(&[0, 1, 3, 2]).is_err());
+        assert!(outfit(&[3, 2, 4, 5]).is_err());
+
+        let mut outfits = Outfits::new();
+        assert!(outfits.add(outfit(&[0, 1, 2]).unwrap()).is_none());
+        let again = Outfit::new(1, vec![
+            Rc::downgrade(&clothes[2]),
+            Rc::downgrade(&clothes[0]),
+            Rc::downgrade(&clothes[1]),
+        ]);
+        assert!(outfits.add(again.unwrap()).is_some());
+
+        // extras are picked once each, with a single outerwear.
+        let all = Clothes { list: clothes.clone() };
+        let mut buffer = OutfitBuffer::new();
+        buffer.clothes = [3, 2].iter().map(|id| Rc::downgrade(&clothes[*id])).collect();
+        let ids = |extras: Clothes| -> Vec<u32> {
+            extras.list.iter().map(|clth| clth.borrow().id).collect()
+        };
+        assert_eq!(vec![4, 5, 6], ids(buffer.extras(&all)));
+        buffer.clothes.push(Rc::downgrade(&clothes[6]));
+        assert_eq!(vec![4, 5], ids(buffer.extras(&all)));
+        buffer.clothes.push(Rc::downgrade(&clothes[4]));
+        assert!(buffer.extras(&all).list.is_empty());
+        assert!(buffer.to_outfit(1).is_ok());
+    }
+
     #[test]
     pub fn create_color_by_hex() {
         let color = Rgb::try_from_hex("FFFFFF").unwrap();
@@ -774,15 +1197,53 @@ mod tests {
 
     #[test]
     pub fn kind_from_str() {
-        assert!(
-            matches!(Kind::from_str("Chest").unwrap(), Kind::Chest)
-        );
-        assert!(
-            matches!(Kind::from_str("Leg").unwrap(), Kind::Leg)
-        );
-        assert!(
-            matches!(Kind::from_str("Foot").unwrap(), Kind::Foot)
-        );
+        let mut kinds = Kinds::new();
+        assert_eq!(Slot::Top, kinds.parse("Chest").unwrap().slot);
+        assert_eq!(Slot::Bottom, kinds.parse("leg").unwrap().slot);
+        assert_eq!(Slot::Footwear, kinds.parse("FOOT").unwrap().slot);
+        assert!(kinds.parse("dress").is_err());
+
+        kinds.add(Kind::new("Dress", Slot::from_str("full-body").unwrap())).unwrap();
+        assert_eq!(Slot::FullBody, kinds.parse("dress").unwrap().slot);
+        assert!(kinds.add(Kind::new("DRESS", Slot::Top)).is_err());
+        assert!(kinds.add(Kind::new("chest", Slot::Top)).is_err());
+        assert_eq!(vec!["Chest", "Leg", "Foot", "Dress"], kinds.names());
+        assert_eq!(1, kinds.custom().len());
+        assert!(kinds.remove("leg").is_err());
+        kinds.remove("dress").unwrap();
+        assert!(kinds.custom().is_empty());
+    }
+
+    #[test]
+    pub fn slot_from_str() {
+        for slot in Slot::ALL {
+            assert_eq!(slot, Slot::from_str(&slot.to_string()).unwrap());
+        }
+        assert!(Slot::from_str("head").is_err());
+    }
+
+    #[test]
+    pub fn group_by_kind() {
+        let mut kinds = Kinds::new();
+        let hat = kinds.add(Kind::new("Hat", Slot::Accessory)).unwrap();
+        let date = NaiveDate::from_ymd(2023, 1, 2);
+        let style = Rc::new(Style::new("style"));
+        let clth = |id, kind| {
+            Clth::new(id, kind, Sex::Male, Size::M, Rgb(0, 0, 0), Target::Keep, date,
+                Rc::clone(&style))
+        };
+        let clothes = Clothes::from(vec![
+            clth(0, Rc::clone(&hat)),
+            clth(1, kinds.parse("chest").unwrap()),
+            clth(2, hat),
+        ]);
+
+        let map = clothes.map_by_kind(&kinds);
+        assert_eq!(4, map.len());
+        assert_eq!(2, map["Hat"].list.len());
+        assert_eq!(1, map["Chest"].list.len());
+        assert!(map["Foot"].list.is_empty());
+        assert_eq!(1, clothes.of_slots(&[Slot::Top]).list.len());
     }
 
     #[test]
@@ -818,12 +1279,25 @@ mod tests {
         );
     }
 
+    #[test]
+    pub fn status_from_str() {
+        let date = NaiveDate::from_ymd(2023, 1, 2);
+        assert_eq!(Status::Active, Status::from_str("active").unwrap());
+        assert_eq!(Status::Sold(date), Status::from_str("Sold on 2023-01-02").unwrap());
+        assert_eq!(Status::Donated(date), Status::from_str(&Status::Donated(date).to_string())
+            .unwrap());
+        assert!(Status::from_str("Sold").is_err());
+        assert!(Status::from_str("Lost on 2023-01-02").is_err());
+    }
+
+
     #[test]
     pub fn target_from_str() {
         assert!(Target::from_str("invalid").is_err());
-        assert!(
-            matches!(Target::from_str("Sale for $10.00").unwrap(), Target::Sale(1000))
-        );
+        assert_eq!(Target::Sale(Money::usd(1000)), Target::from_str("Sale for $10.00").unwrap());
+        let target = Target::from_str("sale for R$ 20,75").unwrap();
+        assert_eq!("Sale for 20.75 BRL", target.encode());
+        assert_eq!(target, Target::from_str(&target.encode()).unwrap());
         assert!(
             matches!(Target::from_str("Donation").unwrap(), Target::Donation) 
         );
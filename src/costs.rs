@@ -0,0 +1,185 @@
+//! Report of what the clothes cost.
+//!
+//! Clothes with a purchase price (see `Clth::cost`) get their cost per wear
+//! and, once sold or put on sale, how much of what was paid is lost. A
+//! resale is only compared with a purchase price of the same currency.
+
+use std::fmt::Display;
+
+use crate::Data;
+use crate::closet::Target;
+use crate::money::{ Amounts, Money };
+
+/// What a clothing returned or is expected to return.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resale {
+    Sold(Money),
+    Asked(Money),
+}
+
+impl Resale {
+    pub fn price(&self) -> &Money {
+        match self {
+            Resale::Sold(price) | Resale::Asked(price) => price,
+        }
+    }
+}
+
+pub struct Line {
+    pub clth: u32,
+    pub cost: Money,
+    pub wears: u32,
+    pub resale: Option<Resale>,
+}
+
+impl Line {
+    /// Cost of each wear, in cents of the cost currency. Computed on 128
+    /// bits so that rounding can't overflow; the result is never above the
+    /// cost.
+    pub fn per_wear(&self) -> Option<u64> {
+        let wears = u128::from(self.wears);
+        match wears {
+            0 => None,
+            _ => u64::try_from((u128::from(self.cost.cents) + wears / 2) / wears).ok(),
+        }
+    }
+
+    /// What was paid minus what the resale returns, in cents. It is only
+    /// known when both are in the same currency.
+    pub fn depreciation(&self) -> Option<i128> {
+        let price = self.resale.as_ref()?.price();
+        if price.currency != self.cost.currency {
+            return None;
+        }
+        Some(i128::from(self.cost.cents) - i128::from(price.cents))
+    }
+
+    /// `cents` of the cost currency. They are at most the cost or the
+    /// resale price, which both fit.
+    fn amount(&self, cents: u128) -> Money {
+        Money::new(u64::try_from(cents).unwrap_or(u64::MAX), self.cost.currency.clone())
+    }
+}
+
+impl Display for Line {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Clothing {}: {}, worn {} time(s)", self.clth, self.cost, self.wears)?;
+        if let Some(cents) = self.per_wear() {
+            write!(f, " ({} per wear)", self.amount(u128::from(cents)))?;
+        }
+        match &self.resale {
+            Some(Resale::Sold(price)) => write!(f, ", sold for {}", price)?,
+            Some(Resale::Asked(price)) => write!(f, ", on sale for {}", price)?,
+            None => (),
+        }
+        match self.depreciation() {
+            Some(lost) if lost >= 0 && self.cost.cents > 0 => write!(f, ", depreciation {} ({}%)",
+                self.amount(lost.unsigned_abs()), lost * 100 / i128::from(self.cost.cents)),
+            Some(lost) if lost >= 0 => {
+                write!(f, ", depreciation {}", self.amount(lost.unsigned_abs()))
+            },
+            Some(lost) => write!(f, ", gain {}", self.amount(lost.unsigned_abs())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The costs of every clothing with a purchase price, archived or not.
+pub struct Report {
+    pub lines: Vec<Line>,
+    /// What was spent on them.
+    pub spent: Amounts,
+}
+
+impl Report {
+    pub fn new(data: &Data) -> Report {
+        let mut report = Report { lines: Vec::new(), spent: Amounts::new() };
+
+        for clth in data.clothes.list.iter() {
+            let clth = clth.borrow();
+            let cost = match &clth.cost {
+                Some(cost) => cost.clone(),
+                None => continue,
+            };
+            let resale = match (data.sales.get(clth.id), &clth.target) {
+                (Some(sale), _) => Some(Resale::Sold(sale.price.clone())),
+                (None, Target::Sale(price)) if clth.status.is_active() => {
+                    Some(Resale::Asked(price.clone()))
+                },
+                _ => None,
+            };
+
+            report.spent.add(&cost);
+            report.lines.push(Line { clth: clth.id, cost, wears: clth.wears, resale });
+        }
+        report.lines.sort_by_key(|line| line.clth);
+        report
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.lines.is_empty() {
+            return write!(f, "No purchase prices to report!");
+        }
+        for line in self.lines.iter() {
+            writeln!(f, "{}", line)?;
+        }
+        write!(f, "Total spent: {}", self.spent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::money::Currency;
+    use crate::sales::Sale;
+    use crate::storage::read_data;
+    use crate::storage::tests::closet;
+
+    #[test]
+    fn report_costs() {
+        let mut data = closet();
+        let eur = Currency::new("EUR").unwrap();
+        crate::set_cost(&mut data, 0, Some(Money::usd(4990))).unwrap();
+        crate::set_cost(&mut data, 2, Some(Money::new(1000, eur))).unwrap();
+        for id in [0, 0, 0, 2] {
+            crate::wear_clth(&mut data, id).unwrap();
+        }
+
+        let report = Report::new(&data);
+        assert_eq!(vec![0, 2], report.lines.iter().map(|line| line.clth).collect::<Vec<_>>());
+        assert_eq!(Some(1663), report.lines[0].per_wear());
+        assert_eq!(Some(2915), report.lines[0].depreciation());
+        assert_eq!(None, report.lines[1].depreciation());
+        assert_eq!("Clothing 0: $49.90, worn 3 time(s) ($16.63 per wear), on sale \
+            for $20.75, depreciation $29.15 (58%)", report.lines[0].to_string());
+        assert!(report.to_string().ends_with("Total spent: €10,00 + $49.90"));
+
+        let again = read_data(&data.to_toml(), false).unwrap().0;
+        let clth = again.clothes.get(0).unwrap().borrow().clone();
+        assert_eq!((Some(Money::usd(4990)), 3), (clth.cost, clth.wears));
+
+        let date = NaiveDate::from_ymd(2023, 1, 2);
+        let price = Money::usd(6000);
+        crate::sell_clth(&mut data, Sale { clth: 0, date, price: price.clone(), channel: None })
+            .unwrap();
+        let report = Report::new(&data);
+        assert_eq!(Some(Resale::Sold(price)), report.lines[0].resale);
+        assert!(report.lines[0].to_string().ends_with("gain $10.10"));
+    }
+
+    #[test]
+    fn report_huge_amounts() {
+        let line = |cost, wears, resale| Line { clth: 0, cost: Money::usd(cost), wears, resale };
+        let worn = line(u64::MAX, 2, Some(Resale::Sold(Money::usd(0))));
+        assert_eq!(Some(u64::MAX / 2 + 1), worn.per_wear());
+        assert_eq!(Some(i128::from(u64::MAX)), worn.depreciation());
+        assert!(worn.to_string().ends_with(", depreciation $184467440737095516.15 (100%)"));
+
+        let sold = line(0, 1, Some(Resale::Asked(Money::usd(u64::MAX))));
+        assert_eq!(Some(-i128::from(u64::MAX)), sold.depreciation());
+        assert!(sold.to_string().ends_with(", gain $184467440737095516.15"));
+    }
+}
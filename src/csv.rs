@@ -0,0 +1,318 @@
+//! CSV import and export of the clothing inventory.
+//!
+//! The first row holds the column names: `id`, `kind`, `sex`, `size`,
+//! `color`, `target`, `price`, `purchase_date` and `style`, where `kind` is
+//! the name of a kind of the closet (see `closet::Kinds`). On import the
+//! columns may come in any order, unknown columns are ignored, and `id`,
+//! `price` and `purchase_date` are optional: clothes without an id get a new
+//! one and clothes without a purchase date are dated today. `target` is one of
+//! `keep`, `donation` or `sale`, and `price` is only filled for sales. Prices
+//! are exported like `20.75 EUR` and imported in any form `Money` reads.
+//! `size` is any size of `sizes::Size` that fits the kind.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use chrono::{ Local, NaiveDate };
+
+use crate::Data;
+use crate::closet::{ Clothes, ClthBuffer, Kinds, Rgb, Sex, Target };
+use crate::money::Money;
+use crate::sizes::Size;
+use crate::storage::ParseError;
+use crate::store::Change;
+
+pub const COLUMNS: [&str; 9] = [
+    "id", "kind", "sex", "size", "color", "target", "price", "purchase_date", "style"
+];
+
+const REQUIRED: [&str; 6] = ["kind", "sex", "size", "color", "target", "style"];
+
+/// A clothing read from a CSV row, not yet added to the closet.
+pub struct CsvClth {
+    pub line: u32,
+    pub id: Option<u32>,
+    pub buffer: ClthBuffer,
+    pub purchase_date: NaiveDate,
+    pub style: String,
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+/// Splits `text` into records, pairing each one with the line it starts on.
+pub fn records(text: &str) -> Result<Vec<(u32, Vec<String>)>, ParseError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut line = 1;
+    let mut start = 1;
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match (quoted, ch) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            (true, '"') => quoted = false,
+            (true, ch) => {
+                if ch == '\n' { line += 1; }
+                field.push(ch);
+            },
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') if chars.peek() == Some(&'\n') => (),
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push((start, std::mem::take(&mut record)));
+                line += 1;
+                start = line;
+            },
+            (false, ch) => field.push(ch),
+        }
+    }
+
+    if quoted {
+        return Err(ParseError::new(start, String::from("Unterminated quoted field.")));
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+
+    // blank lines carry no record.
+    records.retain(|(_, record)| !(record.len() == 1 && record[0].trim().is_empty()));
+    Ok(records)
+}
+
+pub fn export(clothes: &Clothes) -> String {
+    let mut lines = vec![COLUMNS.join(",")];
+    for clth in clothes.list.iter() {
+        let clth = clth.borrow();
+        let (target, price) = match &clth.target {
+            Target::Sale(price) => ("sale", price.to_code()),
+            Target::Donation => ("donation", String::new()),
+            Target::Keep => ("keep", String::new()),
+        };
+        let row = [
+            clth.id.to_string(),
+            clth.kind.to_string().to_lowercase(),
+            clth.sex.to_string().to_lowercase(),
+            clth.size.to_string(),
+            clth.color.to_string(),
+            String::from(target),
+            price,
+            clth.purchase_date.to_string(),
+            clth.style.name.clone(),
+        ];
+        lines.push(row.iter().map(|field| escape(field)).collect::<Vec<_>>().join(","));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn read_row(line: u32, row: &HashMap<&str, &str>, kinds: &Kinds) -> Result<CsvClth, String> {
+    let get = |name: &str| row.get(name).map(|value| value.trim()).unwrap_or("");
+    let field = |name: &str| match get(name) {
+        "" => Err(format!("Missing '{}'.", name)),
+        value => Ok(value),
+    };
+    let parse = |name: &str, value: &str, err: &str| format!("{} '{}': {}", name, value, err);
+
+    let id = match get("id") {
+        "" => None,
+        value => Some(value.parse().map_err(|_| parse("id", value, "Invalid id."))?),
+    };
+
+    let mut buffer = ClthBuffer::new();
+    let kind = field("kind")?;
+    buffer.kind = Some(kinds.parse(kind).map_err(|err| parse("kind", kind, err))?);
+    let sex = field("sex")?;
+    buffer.sex = Some(Sex::from_str(sex).map_err(|err| parse("sex", sex, err))?);
+    let size = field("size")?;
+    let size = Size::from_str(size).map_err(|err| parse("size", size, err))?;
+    if !buffer.kind.as_ref().unwrap().sizes.fits(&size) {
+        return Err(format!("size '{}': The size doesn't fit the kind.", size));
+    }
+    buffer.size = Some(size);
+
+    let color = field("color")?;
+    buffer.color = match Rgb::try_from_hex(color.trim_start_matches('#')) {
+        Some(rgb) => Some(rgb),
+        None => return Err(parse("color", color, "Invalid color.")),
+    };
+
+    let target = field("target")?;
+    buffer.target = match (target.to_lowercase().as_str(), get("price")) {
+        ("sale", "") => return Err(String::from("A sale requires a 'price'.")),
+        ("sale", price) => {
+            let price = Money::from_str(price).map_err(|err| parse("price", price, err))?;
+            Some(Target::Sale(price))
+        },
+        (_, "") => Some(Target::from_str(target).map_err(|err| parse("target", target, err))?),
+        (_, _) => return Err(String::from("'price' is only valid for a sale.")),
+    };
+
+    let purchase_date = match get("purchase_date") {
+        "" => Local::today().naive_local(),
+        value => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| parse("purchase_date", value, "Invalid date."))?,
+    };
+
+    let style = field("style")?.to_lowercase();
+    Ok(CsvClth { line, id, buffer, purchase_date, style })
+}
+
+/// Reads every row of `text`, of the kinds of `kinds`, reporting all the
+/// invalid ones.
+pub fn read(text: &str, kinds: &Kinds) -> Result<Vec<CsvClth>, Vec<ParseError>> {
+    let mut records = records(text).map_err(|err| vec![err])?.into_iter();
+    let (_, header) = match records.next() {
+        Some(value) => value,
+        None => return Ok(Vec::new()),
+    };
+
+    let header: Vec<String> = header.iter().map(|col| col.trim().to_lowercase()).collect();
+    let missing: Vec<_> = REQUIRED
+        .iter()
+        .filter(|col| !header.iter().any(|name| name == *col))
+        .map(|col| ParseError::new(1, format!("Missing '{}' column.", col)))
+        .collect();
+    if !missing.is_empty() {
+        return Err(missing);
+    }
+
+    let mut clothes = Vec::new();
+    let mut errors = Vec::new();
+    for (line, record) in records {
+        if record.len() > header.len() {
+            errors.push(ParseError::new(line, format!(
+                "Expected at most {} fields, found {}.", header.len(), record.len())));
+            continue;
+        }
+
+        let row: HashMap<&str, &str> = header
+            .iter()
+            .map(String::as_str)
+            .zip(record.iter().map(String::as_str))
+            .collect();
+        match read_row(line, &row, kinds) {
+            Ok(clth) => clothes.push(clth),
+            Err(msg) => errors.push(ParseError::new(line, msg)),
+        }
+    }
+
+    if errors.is_empty() { Ok(clothes) } else { Err(errors) }
+}
+
+/// Adds the clothes of `text` to `data`, returning their ids. Nothing is
+/// added unless every row is valid.
+pub fn import(text: &str, data: &mut Data) -> Result<Vec<u32>, Vec<ParseError>> {
+    let rows = read(text, &data.kinds)?;
+
+    let mut errors = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        let id = match row.id {
+            Some(id) => id,
+            None => continue,
+        };
+        if data.clothes.get(id).is_some() {
+            errors.push(ParseError::new(row.line, format!("Id {} is already in use.", id)));
+        } else if rows[..index].iter().any(|other| other.id == Some(id)) {
+            errors.push(ParseError::new(row.line, format!("Id {} is repeated.", id)));
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    // rows with an id go first, so new ids can't take the ones still to come.
+    let mut order: Vec<usize> = (0..rows.len()).collect();
+    order.sort_by_key(|index| rows[*index].id.is_none());
+
+    let mut rows: Vec<Option<CsvClth>> = rows.into_iter().map(Some).collect();
+    let mut ids = vec![0; rows.len()];
+    for index in order {
+        let row = rows[index].take().unwrap();
+        let id = row.id.unwrap_or_else(|| data.clothes.request_id());
+        let style = data.styles.get_or_add(&row.style);
+        data.clothes.add(row.buffer.to_clth(id, row.purchase_date, style));
+        data.changes.push(Change::AddClth(id));
+        ids[index] = id;
+    }
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "\
+kind,size,color,target,price,sex,style,notes
+chest,M,#FF00EE,sale,20.75,male,summer,\"torn, \"\"small\"\" hole\"
+leg,L,000000,donation,,female,winter,
+foot,XL,FFFFFF,sale,\"R$ 9,90\",unissex,summer,\"multi
+line\"
+";
+
+    #[test]
+    fn split_records() {
+        let records = records(SHEET).unwrap();
+        assert_eq!(4, records.len());
+        assert_eq!("torn, \"small\" hole", records[1].1[7]);
+        assert_eq!(4, records[3].0);
+        assert_eq!("multi\nline", records[3].1[7]);
+    }
+
+    #[test]
+    fn import_rows() {
+        let mut data = Data::new();
+        let ids = import(SHEET, &mut data).unwrap();
+        assert_eq!(vec![0, 1, 2], ids);
+        assert_eq!(Target::Sale(Money::usd(2075)), data.clothes.get(0).unwrap().borrow().target);
+        assert_eq!(Size::XL, data.clothes.get(2).unwrap().borrow().size);
+        assert_eq!("Sale for 9.90 BRL", data.clothes.get(2).unwrap().borrow().target.encode());
+    }
+
+    #[test]
+    fn report_every_bad_row() {
+        let sheet = "\
+kind,sex,size,color,target,price,style
+hat,male,M,#FF00EE,keep,,summer
+chest,male,M,#FF00EE,keep,,summer
+
+leg,male,M,nope,sale,,summer
+";
+        let errors = read(sheet, &Kinds::new()).err().unwrap();
+        let lines: Vec<u32> = errors.iter().map(|err| err.line).collect();
+        assert_eq!(vec![2, 5], lines);
+        assert!(errors[1].msg.contains("color"));
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut data = Data::new();
+        import(SHEET, &mut data).unwrap();
+        let text = export(&data.clothes);
+        assert!(text.starts_with("id,kind,sex,size,color,target,price,purchase_date,style\n"));
+
+        let mut again = Data::new();
+        assert_eq!(vec![0, 1, 2], import(&text, &mut again).unwrap());
+        assert_eq!(text, export(&again.clothes));
+        assert!(import(&text, &mut again).is_err());
+    }
+
+    #[test]
+    fn keep_given_ids() {
+        let sheet = "kind,sex,size,color,target,style,id\n\
+            chest,male,M,#FF00EE,keep,summer,\n\
+            leg,male,M,#FF00EE,keep,summer,0\n";
+        let mut data = Data::new();
+        assert_eq!(vec![1, 0], import(sheet, &mut data).unwrap());
+    }
+}
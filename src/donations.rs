@@ -0,0 +1,173 @@
+//! Batches of clothes given away, kept as `[[donation]]` entries of the data
+//! file. Each batch can print a Markdown receipt for tax paperwork.
+
+use std::fmt::Display;
+use chrono::NaiveDate;
+
+use crate::closet::{ Clothes, ErrMsg };
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Donation {
+    pub id: u32,
+    pub recipient: String,
+    pub date: NaiveDate,
+    /// Ids of the clothes donated.
+    pub clothes: Vec<u32>,
+}
+
+impl Donation {
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("id"), toml::Value::from(self.id));
+        table.insert(String::from("recipient"), toml::Value::from(self.recipient.as_str()));
+        table.insert(String::from("date"), toml::Value::from(self.date.to_string()));
+        table.insert(String::from("clothes"), toml::Value::from(self.clothes.clone()));
+        toml::Value::Table(table)
+    }
+
+    /// Builds the Markdown receipt of the batch, listing its clothes as found
+    /// in `clothes`.
+    pub fn receipt(&self, clothes: &Clothes) -> String {
+        let mut lines = vec![
+            format!("# Donation receipt {}", self.id),
+            String::new(),
+            format!("- Recipient: {}", self.recipient),
+            format!("- Date: {}", self.date),
+            format!("- Items: {}", self.clothes.len()),
+            String::new(),
+            String::from("| Id | Kind | Size | Color | Purchase date |"),
+            String::from("|----|------|------|-------|---------------|"),
+        ];
+
+        for id in self.clothes.iter() {
+            let row = match clothes.get(*id) {
+                Some(clth) => {
+                    let clth = clth.borrow();
+                    format!("| {} | {} | {} | {} | {} |",
+                        clth.id, clth.kind, clth.size, clth.color, clth.purchase_date)
+                },
+                None => format!("| {} | ? | ? | ? | ? |", id),
+            };
+            lines.push(row);
+        }
+        lines.join("\n")
+    }
+}
+
+impl Display for Donation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ids: Vec<String> = self.clothes.iter().map(u32::to_string).collect();
+        write!(f, "[ Donation {} ] {} to {}: clothes {}",
+            self.id, self.date, self.recipient, ids.join(", "))
+    }
+}
+
+#[derive(Default)]
+pub struct Donations {
+    pub list: Vec<Donation>,
+}
+
+impl Donations {
+    pub fn new() -> Donations {
+        Donations { list: Vec::new() }
+    }
+
+    pub fn add(&mut self, donation: Donation) -> Option<ErrMsg> {
+        if self.get(donation.id).is_some() {
+            return Some("A donation with the same id already exists.");
+        }
+        if donation.clothes.iter().any(|id| self.of(*id).is_some()) {
+            return Some("A clothing can only be donated once.");
+        }
+        self.list.push(donation);
+        None
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Donation> {
+        self.list.iter().find(|donation| donation.id == id)
+    }
+
+    /// Returns the donation the clothing `clth` was given in.
+    pub fn of(&self, clth: u32) -> Option<&Donation> {
+        self.list.iter().find(|donation| donation.clothes.contains(&clth))
+    }
+
+    pub fn remove(&mut self, id: u32) -> Result<Donation, ErrMsg> {
+        match self.list.iter().position(|donation| donation.id == id) {
+            Some(index) => Ok(self.list.remove(index)),
+            None => Err("Donation not found."),
+        }
+    }
+
+    pub fn request_id(&self) -> u32 {
+        let ids: Vec<u32> = self.list.iter().map(|donation| donation.id).collect();
+        let mut new_id = 0;
+        while ids.contains(&new_id) {
+            new_id += 1
+        }
+        new_id
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(self.list.iter().map(|donation| donation.to_toml()).collect())
+    }
+}
+
+impl Display for Donations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.list.is_empty() {
+            return write!(f, "No donations to display!");
+        }
+        let lines: Vec<String> = self.list.iter().map(|donation| donation.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closet::{ Status, Target };
+    use crate::storage::read_data;
+    use crate::storage::tests::closet;
+
+    #[test]
+    fn donate_batch() {
+        let mut data = closet();
+        let date = NaiveDate::from_ymd(2023, 2, 1);
+
+        // only clothes marked for donation can be donated.
+        assert!(crate::donate(&mut data, "Red Cross", date, &[0, 1]).is_err());
+        assert!(crate::donate(&mut data, "Red Cross", date, &[]).is_err());
+        let id = crate::donate(&mut data, "Red Cross", date, &[1]).unwrap();
+        assert_eq!(Status::Donated(date), data.clothes.get(1).unwrap().borrow().status);
+        assert!(data.outfits.list.is_empty());
+        assert!(crate::donate(&mut data, "Red Cross", date, &[1]).is_err());
+
+        let receipt = data.donations.get(id).unwrap().receipt(&data.clothes);
+        assert!(receipt.contains("- Recipient: Red Cross"));
+        assert!(receipt.ends_with("| 1 | Foot | M | #000000 | 2022-08-16 |"));
+
+        let again = read_data(&data.to_toml(), false).unwrap().0;
+        assert_eq!(data.donations.list, again.donations.list);
+        assert!(crate::rm_clth(&mut data, 1).is_err());
+    }
+
+    #[test]
+    fn undo_donation_at_once() {
+        let mut data = closet();
+        let date = NaiveDate::from_ymd(2023, 2, 1);
+        data.clothes.get(2).unwrap().borrow_mut().target = Target::Donation;
+        let id = crate::donate(&mut data, "Red Cross", date, &[1, 2]).unwrap();
+
+        let undone = crate::history::undo(&mut data).unwrap().to_string();
+        assert_eq!(format!("donate batch {}", id), undone);
+        assert!(data.donations.list.is_empty());
+        assert_eq!(data.clothes.list.len(), data.clothes.active().list.len());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert!(crate::history::undo(&mut data).is_err());
+
+        crate::history::redo(&mut data).unwrap();
+        assert_eq!(Status::Donated(date), data.clothes.get(2).unwrap().borrow().status);
+        assert!(data.donations.get(id).is_some());
+    }
+}
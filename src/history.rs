@@ -0,0 +1,401 @@
+//! Undo and redo of the edits made to the closet.
+//!
+//! Every edit made through the shared operations of the crate (`add_clth`,
+//! `rm_clth`, `add_outfit`, ...) is recorded on `Data::history` as an `Op`
+//! holding what is needed to revert it. Undoing and redoing go through the
+//! same changes as any other edit, so they are persisted as well.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::Data;
+use crate::closet::{ Clth, ErrMsg, Kind, Outfit };
+use crate::donations::Donation;
+use crate::sales::Sale;
+use crate::store::Change;
+
+/// An outfit, by its id and the ids of its clothes.
+pub type OutfitIds = (u32, Vec<u32>);
+
+/// A reversible edit of the closet.
+pub enum Op {
+    /// `new_style` is set if the style of the clothing was created with it.
+    AddClth { clth: Clth, new_style: bool },
+    /// Holds the outfits that were removed along with the clothing.
+    RemoveClth { clth: Clth, outfits: Vec<OutfitIds> },
+    UpdateClth { before: Clth, after: Clth, new_style: bool },
+    AddOutfit(OutfitIds),
+    RemoveOutfit(OutfitIds),
+    AddSale(Sale),
+    AddDonation(Donation),
+    AddKind(Kind),
+    /// The edits made by a single action, undone and redone at once.
+    Batch(Vec<Op>),
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::AddClth { clth, .. } => write!(f, "add clothing {}", clth.id),
+            Op::RemoveClth { clth, outfits } if outfits.is_empty() => {
+                write!(f, "remove clothing {}", clth.id)
+            },
+            Op::RemoveClth { clth, outfits } => {
+                write!(f, "remove clothing {} and {} outfit(s)", clth.id, outfits.len())
+            },
+            Op::UpdateClth { after, .. } => write!(f, "update clothing {}", after.id),
+            Op::AddOutfit((id, _)) => write!(f, "add outfit {}", id),
+            Op::RemoveOutfit((id, _)) => write!(f, "remove outfit {}", id),
+            Op::AddSale(sale) => write!(f, "sell clothing {}", sale.clth),
+            Op::AddDonation(donation) => write!(f, "donate batch {}", donation.id),
+            Op::AddKind(kind) => write!(f, "add kind {}", kind.name),
+            // the last edit is the one the action is about.
+            Op::Batch(ops) => match ops.last() {
+                Some(op) => write!(f, "{}", op),
+                None => write!(f, "nothing"),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    pub done: Vec<Op>,
+    pub undone: Vec<Op>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History { done: Vec::new(), undone: Vec::new() }
+    }
+
+    /// Records an edit that was just made. Whatever was undone can't be
+    /// redone anymore.
+    pub fn record(&mut self, op: Op) {
+        self.done.push(op);
+        self.undone.clear();
+    }
+
+    /// Merges the edits recorded since there were `since` of them into one,
+    /// so that they are undone at once.
+    pub fn merge_since(&mut self, since: usize) {
+        if self.done.len() > since + 1 {
+            let ops = self.done.split_off(since);
+            self.done.push(Op::Batch(ops));
+        }
+    }
+}
+
+fn insert_clth(data: &mut Data, clth: &Clth) -> Result<(), ErrMsg> {
+    let mut clth = clth.clone();
+    clth.style = data.styles.get_or_add(&clth.style.name);
+    let id = clth.id;
+    if let Some(msg) = data.clothes.add(clth) {
+        return Err(msg);
+    }
+    data.changes.push(Change::AddClth(id));
+    Ok(())
+}
+
+fn remove_clth(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    data.outfits.remove_using(id);
+    data.clothes.remove(id)?;
+    data.changes.push(Change::RemoveClth(id));
+    Ok(())
+}
+
+/// Replaces the fields of the clothing with the same id as `clth`.
+fn replace_clth(data: &mut Data, clth: &Clth) -> Result<(), ErrMsg> {
+    let mut clth = clth.clone();
+    clth.style = data.styles.get_or_add(&clth.style.name);
+    let id = clth.id;
+    match data.clothes.get(id) {
+        Some(rc) => *rc.borrow_mut() = clth,
+        None => return Err("Clothing not found."),
+    }
+    data.changes.push(Change::UpdateClth(id));
+    Ok(())
+}
+
+/// Removes the style `name` if no clothing uses it anymore.
+fn drop_style(data: &mut Data, name: &str) {
+    let used = data.clothes.list.iter().any(|clth| clth.borrow().style.name == name);
+    if !used {
+        data.styles.remove(name);
+    }
+}
+
+fn insert_outfit(data: &mut Data, (id, ids): &OutfitIds) -> Result<(), ErrMsg> {
+    let mut refs = Vec::new();
+    for clth_id in ids {
+        match data.clothes.get(*clth_id) {
+            Some(clth) => refs.push(Rc::downgrade(clth)),
+            None => return Err("The clothes of the outfit are gone."),
+        }
+    }
+
+    if let Some(msg) = data.outfits.add(Outfit::new(*id, refs)?) {
+        return Err(msg);
+    }
+    data.changes.push(Change::AddOutfit(*id));
+    Ok(())
+}
+
+fn insert_sale(data: &mut Data, sale: &Sale) -> Result<(), ErrMsg> {
+    if let Some(msg) = data.sales.add(sale.clone()) {
+        return Err(msg);
+    }
+    data.changes.push(Change::Sale(sale.clth));
+    Ok(())
+}
+
+fn remove_sale(data: &mut Data, clth: u32) -> Result<(), ErrMsg> {
+    data.sales.remove(clth)?;
+    data.changes.push(Change::Sale(clth));
+    Ok(())
+}
+
+fn insert_donation(data: &mut Data, donation: &Donation) -> Result<(), ErrMsg> {
+    if let Some(msg) = data.donations.add(donation.clone()) {
+        return Err(msg);
+    }
+    data.changes.push(Change::Donation(donation.id));
+    Ok(())
+}
+
+fn remove_donation(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    data.donations.remove(id)?;
+    data.changes.push(Change::Donation(id));
+    Ok(())
+}
+
+fn insert_kind(data: &mut Data, kind: &Kind) -> Result<(), ErrMsg> {
+    data.kinds.add(kind.clone())?;
+    data.changes.push(Change::Kind(kind.name.clone()));
+    Ok(())
+}
+
+/// Removes the kind `name`, which no clothing may be of.
+fn remove_kind(data: &mut Data, name: &str) -> Result<(), ErrMsg> {
+    if data.clothes.list.iter().any(|clth| clth.borrow().kind.name == name) {
+        return Err("Some clothing is of this kind.");
+    }
+    data.kinds.remove(name)?;
+    data.changes.push(Change::Kind(String::from(name)));
+    Ok(())
+}
+
+fn remove_outfit(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    data.outfits.remove(id)?;
+    data.changes.push(Change::RemoveOutfit(id));
+    Ok(())
+}
+
+impl Op {
+    /// Returns the ids of the outfits using the clothing `clth`.
+    pub fn outfits_of(data: &Data, clth: &Rc<RefCell<Clth>>) -> Vec<OutfitIds> {
+        data.outfits.using(clth.borrow().id)
+            .into_iter()
+            .map(|outfit| (outfit.id, outfit.to_ids()))
+            .collect()
+    }
+
+    fn revert(&self, data: &mut Data) -> Result<(), ErrMsg> {
+        match self {
+            Op::AddClth { clth, new_style } => {
+                remove_clth(data, clth.id)?;
+                if *new_style { drop_style(data, &clth.style.name); }
+            },
+            Op::RemoveClth { clth, outfits } => {
+                insert_clth(data, clth)?;
+                for outfit in outfits {
+                    insert_outfit(data, outfit)?;
+                }
+            },
+            Op::UpdateClth { before, after, new_style } => {
+                replace_clth(data, before)?;
+                if *new_style { drop_style(data, &after.style.name); }
+            },
+            Op::AddOutfit((id, _)) => remove_outfit(data, *id)?,
+            Op::RemoveOutfit(outfit) => insert_outfit(data, outfit)?,
+            Op::AddSale(sale) => remove_sale(data, sale.clth)?,
+            Op::AddDonation(donation) => remove_donation(data, donation.id)?,
+            Op::AddKind(kind) => remove_kind(data, &kind.name)?,
+            Op::Batch(ops) => {
+                for op in ops.iter().rev() {
+                    op.revert(data)?;
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn apply(&self, data: &mut Data) -> Result<(), ErrMsg> {
+        match self {
+            Op::AddClth { clth, .. } => insert_clth(data, clth),
+            Op::RemoveClth { clth, .. } => remove_clth(data, clth.id),
+            Op::UpdateClth { after, .. } => replace_clth(data, after),
+            Op::AddOutfit(outfit) => insert_outfit(data, outfit),
+            Op::RemoveOutfit((id, _)) => remove_outfit(data, *id),
+            Op::AddSale(sale) => insert_sale(data, sale),
+            Op::AddDonation(donation) => insert_donation(data, donation),
+            Op::AddKind(kind) => insert_kind(data, kind),
+            Op::Batch(ops) => ops.iter().try_for_each(|op| op.apply(data)),
+        }
+    }
+}
+
+/// Reverts the last edit, returning it.
+pub fn undo(data: &mut Data) -> Result<&Op, ErrMsg> {
+    let op = data.history.done.pop().ok_or("Nothing to undo.")?;
+    if let Err(msg) = op.revert(data) {
+        data.history.done.push(op);
+        return Err(msg);
+    }
+    data.history.undone.push(op);
+    Ok(data.history.undone.last().unwrap())
+}
+
+/// Makes the last undone edit again, returning it.
+pub fn redo(data: &mut Data) -> Result<&Op, ErrMsg> {
+    let op = data.history.undone.pop().ok_or("Nothing to redo.")?;
+    if let Err(msg) = op.apply(data) {
+        data.history.undone.push(op);
+        return Err(msg);
+    }
+    data.history.done.push(op);
+    Ok(data.history.done.last().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::closet::Slot;
+    use crate::storage::tests::closet;
+
+    #[test]
+    fn undo_and_redo_removal() {
+        let mut data = closet();
+
+        crate::rm_clth(&mut data, 1).unwrap();
+        assert!(data.outfits.list.is_empty());
+
+        assert_eq!("remove clothing 1 and 1 outfit(s)", undo(&mut data).unwrap().to_string());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert!(undo(&mut data).is_err());
+
+        redo(&mut data).unwrap();
+        assert!(data.clothes.get(1).is_none() && data.outfits.list.is_empty());
+        assert!(redo(&mut data).is_err());
+
+        undo(&mut data).unwrap();
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert_eq!("summer", data.clothes.get(1).unwrap().borrow().style.name);
+        assert_eq!(vec![Change::RemoveClth(1), Change::AddClth(1), Change::AddOutfit(0),
+            Change::RemoveClth(1), Change::AddClth(1), Change::AddOutfit(0)], data.changes);
+    }
+
+    #[test]
+    fn remove_held_clothing() {
+        let mut data = closet();
+        let held = Rc::clone(data.clothes.get(1).unwrap());
+
+        crate::rm_clth(&mut data, 1).unwrap();
+        assert!(data.outfits.list.is_empty());
+        assert!(!data.to_toml().contains("[[outfit]]"));
+
+        undo(&mut data).unwrap();
+        redo(&mut data).unwrap();
+        assert!(data.outfits.list.is_empty());
+        assert_eq!(1, held.borrow().id);
+    }
+
+    #[test]
+    fn undo_archive_at_once() {
+        let mut data = closet();
+        let date = chrono::NaiveDate::from_ymd(2023, 1, 2);
+        crate::archive_clth(&mut data, 1, crate::closet::Status::Archived(date)).unwrap();
+        assert!(data.outfits.list.is_empty());
+
+        assert_eq!("update clothing 1", undo(&mut data).unwrap().to_string());
+        assert!(data.clothes.get(1).unwrap().borrow().status.is_active());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert!(undo(&mut data).is_err());
+    }
+
+    #[test]
+    fn undo_sale_at_once() {
+        let mut data = closet();
+        let date = chrono::NaiveDate::from_ymd(2023, 1, 2);
+        let sale = Sale { clth: 0, date, price: crate::money::Money::usd(1550), channel: None };
+        crate::sell_clth(&mut data, sale).unwrap();
+        assert!(data.outfits.list.is_empty());
+
+        assert_eq!("sell clothing 0", undo(&mut data).unwrap().to_string());
+        assert!(data.clothes.get(0).unwrap().borrow().status.is_active());
+        assert!(data.sales.list.is_empty());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert!(undo(&mut data).is_err());
+
+        redo(&mut data).unwrap();
+        assert!(!data.clothes.get(0).unwrap().borrow().status.is_active());
+        assert!(data.sales.get(0).is_some() && data.outfits.list.is_empty());
+    }
+
+    #[test]
+    fn remove_only_outfits() {
+        let mut data = closet();
+        let mut top = data.clothes.get(0).unwrap().borrow().clone();
+        top.id = 3;
+        data.clothes.add(top);
+        insert_outfit(&mut data, &(1, vec![3, 2, 1])).unwrap();
+
+        assert_eq!(vec![0, 1], crate::rm_outfits_of(&mut data, 1));
+        assert!(data.outfits.list.is_empty());
+        assert!(data.clothes.get(1).is_some());
+        assert!(crate::rm_outfits_of(&mut data, 1).is_empty());
+
+        assert_eq!("remove outfit 1", undo(&mut data).unwrap().to_string());
+        assert!(data.outfits.get(0).is_some() && data.outfits.get(1).is_some());
+        assert!(undo(&mut data).is_err());
+    }
+
+    #[test]
+    fn undo_style_creation() {
+        let mut data = closet();
+        let mut buffer = crate::closet::ClthBuffer::new();
+        let clth = data.clothes.get(0).unwrap().borrow().clone();
+        buffer.kind = Some(clth.kind);
+        buffer.sex = Some(clth.sex);
+        buffer.size = Some(clth.size);
+        buffer.color = Some(clth.color);
+        buffer.target = Some(clth.target);
+
+        let id = crate::add_clth(&mut data, buffer, "winter", clth.purchase_date, None);
+        assert_eq!(vec!["summer", "winter"], data.styles.names());
+        undo(&mut data).unwrap();
+        assert!(data.clothes.get(id).is_none());
+        assert_eq!(vec!["summer"], data.styles.names());
+
+        redo(&mut data).unwrap();
+        assert_eq!("winter", data.clothes.get(id).unwrap().borrow().style.name);
+        assert_eq!(vec!["summer", "winter"], data.styles.names());
+    }
+
+    #[test]
+    fn undo_kind_creation() {
+        let mut data = closet();
+        crate::add_kind(&mut data, Kind::new("Scarf", Slot::Accessory)).unwrap();
+        assert_eq!("add kind Scarf", undo(&mut data).unwrap().to_string());
+        assert!(data.kinds.get("scarf").is_none());
+        redo(&mut data).unwrap();
+
+        let mut clth = data.clothes.get(0).unwrap().borrow().clone();
+        clth.id = 9;
+        clth.kind = data.kinds.parse("scarf").unwrap();
+        data.clothes.add(clth);
+        assert_eq!(Err("Some clothing is of this kind."), undo(&mut data).map(|_| ()));
+        assert_eq!(vec![Change::Kind(String::from("Scarf")); 3], data.changes);
+    }
+}
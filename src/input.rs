@@ -1,9 +1,14 @@
-use crate::closet::{ Clth, Clothes, Kind, Sex, Size, Target, Rgb, Outfits, Outfit };
+use crate::closet::{ Clth, Clothes, Kind, Kinds, Sex, Slot, Target, Rgb };
+use crate::closet::{ Outfits, Outfit };
+use std::str::FromStr;
 use std::process;
 use std::rc::Rc;
 use std::cell::RefCell;
 use std::io::{ self, Write };
 use crate::menu::{ Menu, Act, Runner};
+use crate::donations::{ Donation, Donations };
+use crate::money::Money;
+use crate::sizes::{ Region, Size, SizeSystem };
 
 pub enum ErrType {
     Recover,
@@ -107,15 +112,48 @@ pub fn menu_from_vec(label: &str, vec: &[&str]) -> Menu<usize> {
     })
 }
 
-pub fn kind() -> Result<Kind, InputErr> {
-    let menu = menu_from_vec("kind menu", &["Chest", "Leg", "Foot", "Exit"]);
+/// Asks for one of the kinds of `kinds`.
+pub fn kind(kinds: &Kinds) -> Result<Rc<Kind>, InputErr> {
+    let mut names = kinds.names();
+    names.push("Exit");
+    let menu = menu_from_vec("kind menu", &names);
     let sel_index = Runner::new(menu).run("Select a type: ").unwrap();
 
-    match sel_index {
-        0 => Ok(Kind::Chest),
-        1 => Ok(Kind::Leg),
-        2 => Ok(Kind::Foot),
-        _ => Err(InputErr::user_abort())
+    match kinds.list().get(sel_index) {
+        Some(kind) => Ok(Rc::clone(kind)),
+        None => Err(InputErr::user_abort())
+    }
+}
+
+/// Asks for the part of the body a new kind is worn on.
+pub fn slot() -> Result<Slot, InputErr> {
+    let mut names: Vec<String> = Slot::ALL.iter().map(|slot| slot.to_string()).collect();
+    names.push(String::from("Exit"));
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let menu = menu_from_vec("slot menu", &names);
+    let sel_index = Runner::new(menu).run("Select a slot: ").unwrap();
+
+    match Slot::ALL.get(sel_index) {
+        Some(slot) => Ok(*slot),
+        None => Err(InputErr::user_abort())
+    }
+}
+
+/// Asks how a new kind worn on `slot` is sized, its slot's usual system
+/// coming first.
+pub fn size_system(slot: Slot) -> Result<SizeSystem, InputErr> {
+    let usual = SizeSystem::of_slot(slot);
+    let mut systems = vec![usual];
+    systems.extend(SizeSystem::ALL.into_iter().filter(|system| *system != usual));
+    let mut names: Vec<String> = systems.iter().map(|system| system.to_string()).collect();
+    names.push(String::from("Exit"));
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let menu = menu_from_vec("size system menu", &names);
+    let sel_index = Runner::new(menu).run("Select a size system: ").unwrap();
+
+    match systems.get(sel_index) {
+        Some(system) => Ok(*system),
+        None => Err(InputErr::user_abort())
     }
 }
 
@@ -131,21 +169,57 @@ pub fn sex() -> Result<Sex, InputErr> {
     }
 }
 
-pub fn size() -> Result<Size, InputErr> {
-    let menu = menu_from_vec("size menu", &["XS", "S", "M", "L", "XL", "Exit"]);
+/// Asks a size of `kind`, in its size system. Letter sizes are picked from
+/// a menu, while the others are typed, like `42` or `9.5 US` for shoes.
+pub fn size(kind: &Kind) -> Result<Size, InputErr> {
+    if kind.sizes == SizeSystem::Letter {
+        return letter_size();
+    }
+
+    let input = read_not_empty(&format!("Enter a size, like {}: ", kind.sizes.examples()));
+    if input.to_lowercase() == "exit" {
+        return Err(InputErr::user_abort());
+    }
+
+    let size = Size::from_str(&input).map_err(InputErr::wrong)?;
+    if !kind.sizes.fits(&size) {
+        return Err(InputErr::wrong("The size doesn't fit the kind."));
+    }
+
+    let others: Vec<String> = Region::ALL
+        .iter()
+        .filter_map(|region| kind.sizes.convert(&size, *region))
+        .filter(|other| *other != size)
+        .map(|other| other.to_string())
+        .collect();
+    if !others.is_empty() {
+        println!("Same as {}.", others.join(" or "));
+    }
+    Ok(size)
+}
+
+fn letter_size() -> Result<Size, InputErr> {
+    let mut names: Vec<String> = Size::LETTERS.iter().map(|size| size.to_string()).collect();
+    names.extend([String::from("Kids"), String::from("Exit")]);
+    let names: Vec<&str> = names.iter().map(String::as_str).collect();
+    let menu = menu_from_vec("size menu", &names);
     let sel_index = Runner::new(menu).run("Select a size: ").unwrap();
 
-    match sel_index {
-        0 => Ok(Size::XS),
-        1 => Ok(Size::S),
-        2 => Ok(Size::M),
-        3 => Ok(Size::L),
-        4 => Ok(Size::XL),
-        _ => Err(InputErr::user_abort())
+    if let Some(size) = Size::LETTERS.get(sel_index) {
+        return Ok(size.clone());
+    }
+    if sel_index > Size::LETTERS.len() {
+        return Err(InputErr::user_abort());
+    }
+
+    let age = read_not_empty("Enter the age of the kid: ");
+    match age.parse() {
+        Ok(age) if age > 0 => Ok(Size::Kids(age)),
+        _ => Err(InputErr::wrong("Invalid age.")),
     }
 }
 
-pub fn target(price: u64) -> Result<Target, InputErr> {
+pub fn target(price: Money) -> Result<Target, InputErr> {
     let menu = menu_from_vec("target menu", &["Donation", "Sale", "Keep", "Exit"]);
     let sel_index = Runner::new(menu).run("Select a target: ").unwrap();
 
@@ -157,16 +231,29 @@ pub fn target(price: u64) -> Result<Target, InputErr> {
     }
 }
 
-pub fn price() -> Result<u64, InputErr> {
-    let price = read_not_empty("Enter a price: $");
+/// Asks a price, like `$20.75`, `€20,75` or `20.75 BRL`. Prices without a
+/// currency are in dollars.
+pub fn price() -> Result<Money, InputErr> {
+    let price = read_not_empty("Enter a price: ");
 
     if price.to_lowercase() == "exit" {
         return Err(InputErr::user_abort());
     }
 
-    match price.parse::<f64>() {
-        Ok(value) => Ok((value * 100.0) as u64),
-        Err(_) => Err(InputErr::wrong("Invalid price!")),
+    Money::from_str(&price).map_err(|msg| {
+        InputErr::wrong(&format!("{} Help: valid prices look like $20.75, €20,75 or 20.75 BRL.",
+            msg))
+    })
+}
+
+/// Asks the purchase price of a clothing, with an optional currency.
+pub fn cost() -> Result<Option<Money>, InputErr> {
+    let input = read_not_empty("Enter the purchase price, like '25.90 EUR' ('none' to clear): ");
+
+    match input.to_lowercase().as_str() {
+        "exit" => Err(InputErr::user_abort()),
+        "none" => Ok(None),
+        _ => Money::from_str(&input).map(Some).map_err(InputErr::wrong),
     }
 }
 
@@ -222,13 +309,100 @@ pub fn select_clth(clothes: &Clothes) -> Result<Rc<RefCell<Clth>>, InputErr> {
     }
 }
 
+/// Asks for a comma separated list of ids of `clothes`.
+pub fn select_clth_ids(clothes: &Clothes) -> Result<Vec<u32>, InputErr> {
+    if clothes.list.is_empty() {
+        return Err(InputErr {
+            class: ErrType::Abort,
+            msg: String::from("No clothes to choose from.")
+        })
+    }
+
+    let input = read_not_empty("Select clothing ids, separated by commas: ").to_lowercase();
+
+    if input == "exit" {
+        return Err(InputErr::user_abort());
+    }
+
+    let mut ids = Vec::new();
+    for item in input.split(',') {
+        let id: u32 = match item.trim().parse() {
+            Ok(id) => id,
+            Err(_) => return Err(InputErr::wrong("Invalid number!")),
+        };
+        if clothes.get(id).is_none() {
+            return Err(InputErr::wrong("Invalid id!"));
+        }
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(ids)
+}
+
 pub fn select_clth_field() -> Result<String, InputErr> {
-    let fields = ["Color", "Kind", "Size", "Sex", "Target","Style" ,"Exit"];
+    let fields = ["Color", "Kind", "Size", "Sex", "Target", "Style", "Purchase price", "Exit"];
     let menu = menu_from_vec("field menu", &fields);
     let sel_index = Runner::new(menu).run("Select a field: ").unwrap();
 
     match sel_index {
-        0..=5 => Ok(fields[sel_index].to_lowercase()),
+        0..=6 => Ok(fields[sel_index].to_lowercase()),
+        _ => Err(InputErr::user_abort()),
+    }
+}
+
+pub fn select_donation(donations: &Donations) -> Result<&Donation, InputErr> {
+    if donations.list.is_empty() {
+        return Err(InputErr {
+            class: ErrType::Abort,
+            msg: String::from("No donations to choose from.")
+        })
+    }
+
+    let input = read_not_empty("Select a donation id: ").to_lowercase();
+
+    if input == "exit" {
+        return Err(InputErr::user_abort());
+    }
+
+    let id: u32 = match input.parse() {
+        Ok(id) => id,
+        Err(_) => return Err(InputErr::wrong("Invalid number!")),
+    };
+
+    match donations.get(id) {
+        Some(donation) => Ok(donation),
+        None => Err(InputErr::wrong("Invalid id!")),
+    }
+}
+
+/// Asks for the buyer of a clothing or the channel it was sold through.
+pub fn channel() -> Result<Option<String>, InputErr> {
+    let input = read_not_empty("Enter the buyer or sales channel ('none' to skip): ");
+
+    match input.to_lowercase().as_str() {
+        "exit" => Err(InputErr::user_abort()),
+        "none" => Ok(None),
+        _ => Ok(Some(input)),
+    }
+}
+
+/// What to remove of a clothing used by some outfits.
+pub enum Removal {
+    /// The clothing along with its outfits.
+    Clth,
+    /// Only the outfits using the clothing.
+    Outfits,
+}
+
+pub fn removal() -> Result<Removal, InputErr> {
+    let options = ["Remove the clothing and its outfits", "Remove only its outfits", "Cancel"];
+    let menu = menu_from_vec("removal menu", &options);
+    let sel_index = Runner::new(menu).run("Select an option: ").unwrap();
+
+    match sel_index {
+        0 => Ok(Removal::Clth),
+        1 => Ok(Removal::Outfits),
         _ => Err(InputErr::user_abort()),
     }
 }
@@ -0,0 +1,154 @@
+//! JSON import and export of the whole closet, mirroring the data file with
+//! plural section names (the document is described in the README).
+
+use serde_json::{ Map, Value };
+
+use crate::Data;
+use crate::storage::FileData;
+
+/// JSON section names and the data file sections they map to.
+const SECTIONS: [(&str, &str); 5] = [
+    ("kinds", "kind"), ("clothes", "clth"), ("outfits", "outfit"), ("sales", "sale"),
+    ("donations", "donation"),
+];
+
+pub fn export(data: &Data) -> String {
+    let mut doc = match serde_json::to_value(data.to_document()) {
+        Ok(Value::Object(doc)) => doc,
+        _ => panic!("The closet document must be a JSON object."),
+    };
+
+    let mut root = Map::new();
+    root.insert(String::from("format_version"),
+        doc.remove("format_version").unwrap_or(Value::Null));
+    root.insert(String::from("styles"), Value::from(data.styles.names()));
+    for (json_key, file_key) in SECTIONS {
+        let items = doc.remove(file_key).unwrap_or_else(|| Value::Array(Vec::new()));
+        root.insert(String::from(json_key), items);
+    }
+
+    serde_json::to_string_pretty(&Value::Object(root))
+        .expect("A JSON value is always serializable.")
+}
+
+pub fn import(text: &str) -> Result<Data, String> {
+    let mut root = match serde_json::from_str(text) {
+        Ok(Value::Object(root)) => root,
+        Ok(_) => return Err(String::from("Expected a JSON object.")),
+        Err(err) => return Err(format!("Invalid JSON: {}", err)),
+    };
+
+    let styles = match root.remove("styles") {
+        None => None,
+        Some(Value::Array(items)) => {
+            let names = items
+                .into_iter()
+                .map(|item| match item {
+                    Value::String(name) => Ok(name),
+                    _ => Err(String::from("'styles' must only hold text.")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(names)
+        },
+        Some(_) => return Err(String::from("'styles' must be an array.")),
+    };
+
+    let mut doc = Map::new();
+    if let Some(version) = root.remove("format_version") {
+        doc.insert(String::from("format_version"), version);
+    }
+    for (json_key, file_key) in SECTIONS {
+        if let Some(items) = root.remove(json_key) {
+            doc.insert(String::from(file_key), items);
+        }
+    }
+    if let Some(key) = root.keys().next() {
+        return Err(format!("'{}' is a invalid section.", key));
+    }
+
+    let doc = toml::Value::try_from(Value::Object(doc))
+        .map_err(|err| format!("Invalid value: {}", err))?;
+    let fdata = FileData::from_value(doc, "").map_err(|err| err.msg)?;
+    let mut data = fdata.to_data().map_err(|diags| diags.to_string())?;
+
+    if let Some(names) = styles {
+        for clth in data.clothes.list.iter() {
+            let name = &clth.borrow().style.name;
+            if !names.contains(name) {
+                return Err(format!("Style '{}' is not in 'styles'.", name));
+            }
+        }
+        for name in names {
+            data.styles.get_or_add(&name);
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLOSET: &str = r#"{
+        "format_version": 3,
+        "styles": ["summer", "winter"],
+        "clothes": [
+            { "id": 0, "kind": "Chest", "sex": "Male", "size": "L",
+              "color": "FF00EE", "target": "Sale for $20.75",
+              "purchase_date": "2022-08-15", "style": "summer" },
+            { "id": 1, "kind": "Foot", "sex": "Male", "size": "M",
+              "color": "FF00EE", "target": "Keep",
+              "purchase_date": "2022-08-15", "style": "summer" },
+            { "id": 2, "kind": "Leg", "sex": "Male", "size": "M",
+              "color": "FF00EE", "target": "Donation",
+              "purchase_date": "2022-08-15", "style": "summer" }
+        ],
+        "outfits": [ { "id": 4, "chest": 0, "leg": 2, "foot": 1 } ]
+    }"#;
+
+    #[test]
+    fn import_and_export() {
+        let data = import(CLOSET).unwrap();
+        assert_eq!(vec!["summer", "winter"], data.styles.names());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+        assert!(data.outfits.get(4).is_some());
+
+        let again = import(&export(&data)).unwrap();
+        assert_eq!(data.to_toml(), again.to_toml());
+        assert_eq!(vec!["summer", "winter"], again.styles.names());
+    }
+
+    #[test]
+    fn import_kinds() {
+        let hat = CLOSET
+            .replace(r#""kind": "Chest""#, r#""kind": "hat""#)
+            .replace(r#""outfits": [ { "id": 4, "chest": 0, "leg": 2, "foot": 1 } ]"#,
+                r#""kinds": [ { "name": "Hat", "slot": "accessory" } ]"#);
+        let data = import(&hat).unwrap();
+        assert_eq!("Hat", data.clothes.get(0).unwrap().borrow().kind.name);
+
+        let json: Value = serde_json::from_str(&export(&data)).unwrap();
+        assert_eq!(1, json["kinds"].as_array().unwrap().len());
+        assert_eq!(data.to_toml(), import(&export(&data)).unwrap().to_toml());
+    }
+
+    #[test]
+    fn export_empty_closet() {
+        let json: Value = serde_json::from_str(&export(&Data::new())).unwrap();
+        assert_eq!(Some(&Value::Array(Vec::new())), json.get("clothes"));
+        assert_eq!(Some(&Value::Array(Vec::new())), json.get("outfits"));
+    }
+
+    #[test]
+    fn reject_invalid_closets() {
+        let bad_outfit = CLOSET.replace(r#""leg": 2"#, r#""leg": 1"#);
+        let missing_style = CLOSET.replace(r#"["summer", "winter"]"#, r#"["winter"]"#);
+        let bad_kind = CLOSET.replace(r#""Foot""#, r#""Hat""#);
+        let float_id = CLOSET.replace(r#""id": 0"#, r#""id": 0.5"#);
+        let unknown = CLOSET.replace(r#""outfits""#, r#""sets""#);
+
+        for text in [bad_outfit, missing_style, bad_kind, float_id, unknown] {
+            assert!(import(&text).is_err(), "{}", text);
+        }
+    }
+}
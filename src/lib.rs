@@ -2,21 +2,54 @@ pub mod closet;
 pub mod menu;
 pub mod input;
 pub mod storage;
+pub mod cli;
+pub mod json;
+pub mod csv;
+pub mod quarantine;
+pub mod store;
+pub mod sqlite;
+pub mod lock;
+pub mod history;
+pub mod sales;
+pub mod donations;
+pub mod costs;
+pub mod money;
+pub mod sizes;
 
 // external
 use std::rc::Rc;
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 
 // intern
 use closet::*;
 use menu::{Act, Menu, Runner};
 use input::InputErr;
+use quarantine::Quarantine;
+use store::{ Change, Store };
+use history::{ History, Op };
+use sales::{ Sale, Sales };
+use donations::{ Donation, Donations };
+use money::Money;
 
 pub struct Data {
     pub clothes: Clothes,
     pub styles: Styles,
+    pub kinds: Kinds,
     pub outfits: Outfits,
+    pub sales: Sales,
+    pub donations: Donations,
+    pub quarantine: Quarantine,
     pub cache: TmpCache,
+    /// Changes not persisted yet (see `store::Store::commit`).
+    pub changes: Vec<Change>,
+    /// Edits made on this session, to be undone (see `history`).
+    pub history: History,
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Data {
@@ -24,13 +57,49 @@ impl Data {
         Data {
             clothes: Clothes::new(),
             styles: Styles::new(),
+            kinds: Kinds::new(),
             outfits: Outfits::new(),
+            sales: Sales::new(),
+            donations: Donations::new(),
+            quarantine: Quarantine::new(),
             cache: TmpCache::new(),
+            changes: Vec::new(),
+            history: History::new(),
         }
     }
 
     pub fn to_toml(&self) -> String {
-        format!("{}\n\n{}", self.clothes.to_toml(), self.outfits.to_toml())
+        toml::to_string(&self.to_document())
+            .expect("Closet data is always representable as TOML.")
+    }
+
+    /// Builds the document written to the data file.
+    pub fn to_document(&self) -> toml::Value {
+        let mut doc = toml::value::Table::new();
+        doc.insert(String::from("format_version"),
+            toml::Value::from(storage::FORMAT_VERSION));
+        if !self.kinds.custom().is_empty() {
+            doc.insert(String::from("kind"), self.kinds.to_toml());
+        }
+        if !self.clothes.list.is_empty() {
+            doc.insert(String::from("clth"), self.clothes.to_toml());
+        }
+        if !self.outfits.list.is_empty() {
+            doc.insert(String::from("outfit"), self.outfits.to_toml());
+        }
+        if !self.sales.list.is_empty() {
+            doc.insert(String::from("sale"), self.sales.to_toml());
+        }
+        if !self.donations.list.is_empty() {
+            doc.insert(String::from("donation"), self.donations.to_toml());
+        }
+        if !self.quarantine.is_empty() {
+            doc.insert(String::from("quarantine"), self.quarantine.to_toml());
+        }
+        if let Some(draft) = self.cache.to_toml() {
+            doc.insert(String::from("draft"), draft);
+        }
+        toml::Value::Table(doc)
     }
 }
 
@@ -39,6 +108,12 @@ pub struct TmpCache {
     pub outfit: Option<OutfitBuffer>
 }
 
+impl Default for TmpCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TmpCache {
     pub fn new() -> TmpCache {
         TmpCache {
@@ -46,67 +121,104 @@ impl TmpCache {
             outfit: None
         }
     }
+
+    /// Builds the `draft` table of the data file, if there is any draft.
+    pub fn to_toml(&self) -> Option<toml::Value> {
+        let mut table = toml::value::Table::new();
+        if let Some(buffer) = self.clth.as_ref().filter(|buffer| !buffer.is_empty()) {
+            table.insert(String::from("clth"), buffer.to_toml());
+        }
+        if let Some(buffer) = self.outfit.as_ref().filter(|buffer| !buffer.is_empty()) {
+            table.insert(String::from("outfit"), buffer.to_toml());
+        }
+
+        if table.is_empty() { None } else { Some(toml::Value::Table(table)) }
+    }
 }
 
-pub fn fill_clth_buffer(cache: &mut ClthBuffer) -> Result<(), InputErr> {
-    if let None = cache.kind {
-        cache.kind = Some(input::kind()?);
+pub fn fill_clth_buffer(cache: &mut ClthBuffer, kinds: &Kinds) -> Result<(), InputErr> {
+    if cache.kind.is_none() {
+        cache.kind = Some(input::kind(kinds)?);
     }
 
-    if let None = cache.sex {
+    if cache.sex.is_none() {
         cache.sex = Some(input::sex()?);
     }
 
-    if let None = cache.size {
-        cache.size = Some(input::size()?);
+    // a draft's kind may have changed since, so its size is checked again.
+    let kind = cache.kind.as_ref().unwrap();
+    if !cache.size.as_ref().is_some_and(|size| kind.sizes.fits(size)) {
+        cache.size = Some(input::size(kind)?);
     }
 
-    if let None = cache.color {
+    if cache.color.is_none() {
         cache.color = Some(input::color()?);
     }
 
-    if let None = cache.price {
+    if cache.price.is_none() {
         cache.price = Some(input::price()?);
     }
     
-    if let None = cache.target {
-        cache.target = Some(input::target(cache.price.unwrap())?);
+    if cache.target.is_none() {
+        cache.target = Some(input::target(cache.price.clone().unwrap())?);
     }
     Ok(())
 }
 
+/// Asks for the clothes missing on `cache` to make a whole outfit: either a
+/// top and a bottom or a full-body clothing, and a footwear.
 pub fn fill_outfit_buffer(cache: &mut OutfitBuffer, clothes: &Clothes)
     -> Result<(), InputErr>
 {
-    let map = clothes.map_by_kind();
-    let chests = map.get("chest").unwrap();
-    let leggings = map.get("leg").unwrap();
-    let footwears = map.get("foot").unwrap();
-
     let separator = ">-<".repeat(10);
-
-    if let None = cache.chest {
-        println!("{}", chests);
-        cache.chest = Some(Rc::downgrade(&input::select_clth(chests)?));
+    let pick = |cache: &mut OutfitBuffer, choices: Clothes| -> Result<(), InputErr> {
+        println!("{}", choices);
+        cache.clothes.push(Rc::downgrade(&input::select_clth(&choices)?));
         println!("{}", separator);
+        Ok(())
+    };
+
+    let slots = cache.slots();
+    if !slots.iter().any(|slot| matches!(slot, Slot::Top | Slot::Bottom | Slot::FullBody)) {
+        // the first clothing tells whether a bottom is needed.
+        pick(cache, clothes.of_slots(&[Slot::Top, Slot::FullBody]))?;
     }
 
-    if let None = cache.leg {
-        println!("{}", leggings);
-        cache.leg = Some(Rc::downgrade(&input::select_clth(leggings)?));
-        println!("{}", separator);
+    let slots = cache.slots();
+    if slots.contains(&Slot::Top) && !slots.contains(&Slot::Bottom) {
+        pick(cache, clothes.of_slots(&[Slot::Bottom]))?;
+    }
+    if slots.contains(&Slot::Bottom) && !slots.contains(&Slot::Top) {
+        pick(cache, clothes.of_slots(&[Slot::Top]))?;
     }
 
-    if let None = cache.foot {
-        println!("{}", footwears);
-        cache.foot = Some(Rc::downgrade(&input::select_clth(footwears)?));
+    if !cache.slots().contains(&Slot::Footwear) {
+        pick(cache, clothes.of_slots(&[Slot::Footwear]))?;
     }
     Ok(())
 }
 
+/// Lets the user add an outerwear and accessories to `cache`, which are
+/// optional and so never kept on a draft.
+fn pick_extras(cache: &mut OutfitBuffer, clothes: &Clothes) {
+    loop {
+        let extras = cache.extras(clothes);
+        if extras.list.is_empty() { break; }
+        let more = InputErr::until_ok(|| input::confirm("Add an outerwear or accessory"));
+        if more != Some(true) { break; }
+
+        println!("{}", extras);
+        match InputErr::until_ok(|| input::select_clth(&extras)) {
+            Some(clth) => cache.clothes.push(Rc::downgrade(&clth)),
+            None => break,
+        }
+    }
+}
+
 pub fn user_add_clth(data: &mut Data) {
     let buffer = match data.cache.clth.take() {
         Some(buffer) => {
+            data.changes.push(Change::Draft);
             let use_cache = InputErr::until_ok(|| {
                 input::confirm("Restore last session")
             }).unwrap();
@@ -115,44 +227,355 @@ pub fn user_add_clth(data: &mut Data) {
         },
         None => ClthBuffer::new(),
     };
+    user_fill_clth(data, buffer);
+}
 
-    let result = InputErr::log_until_ok(buffer, fill_clth_buffer);
+/// Asks for the fields missing on `buffer` and adds the clothing. If the
+/// user gives up, the buffer is kept as a draft.
+pub fn user_fill_clth(data: &mut Data, buffer: ClthBuffer) {
+    let result = InputErr::log_until_ok(buffer, |log| fill_clth_buffer(log, &data.kinds));
     match result {
         Ok(buffer) => {
             let stl_name = InputErr::until_ok(input::style_name);
-            if let None = stl_name { return; }
-            let stl = data.styles.get_or_add(&stl_name.unwrap());
+            if stl_name.is_none() { return; }
 
-            let id = data.clothes.request_id();
             let date = Local::today().naive_local();
-            let new_clth = buffer.to_clth(id, date, stl);
-            data.clothes.add(new_clth);
+            add_clth(data, buffer, &stl_name.unwrap(), date, None);
             println!("Clothing has been added.\n");
         },
-        Err((buffer, _)) => data.cache.clth = Some(buffer),
+        Err((buffer, _)) => {
+            data.cache.clth = Some(buffer);
+            data.changes.push(Change::Draft);
+        },
     }
 }
 
+/// Adds the clothing described by `buffer`, bought for `cost`, to `data`,
+/// returning its new id.
+pub fn add_clth(data: &mut Data, buffer: ClthBuffer, stl_name: &str,
+    date: NaiveDate, cost: Option<Money>) -> u32
+{
+    let new_style = data.styles.get(stl_name).is_none();
+    let stl = data.styles.get_or_add(stl_name);
+    let id = data.clothes.request_id();
+    let mut clth = buffer.to_clth(id, date, stl);
+    clth.cost = cost;
+    data.history.record(Op::AddClth { clth: clth.clone(), new_style });
+    data.clothes.add(clth);
+    data.changes.push(Change::AddClth(id));
+    id
+}
+
+/// Removes a clothing and every outfit that depended on it.
+pub fn rm_clth(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    let clth = data.clothes.get(id).ok_or("Clothing not found.")?;
+    if data.sales.get(id).is_some() {
+        return Err("The clothing has a recorded sale and can't be removed.");
+    }
+    if data.donations.of(id).is_some() {
+        return Err("The clothing was donated and can't be removed.");
+    }
+    let outfits = Op::outfits_of(data, clth);
+    data.outfits.remove_using(id);
+    let clth = data.clothes.remove(id)?.borrow().clone();
+    data.history.record(Op::RemoveClth { clth, outfits });
+    data.changes.push(Change::RemoveClth(id));
+    Ok(())
+}
+
 pub fn user_rm_clth(data: &mut Data) {
-    println!("{}", data.clothes);
-    let clth = match InputErr::until_ok(|| input::select_clth(&data.clothes)) {
+    let clothes = data.clothes.active();
+    println!("{}", clothes);
+    let clth = match InputErr::until_ok(|| input::select_clth(&clothes)) {
         Some(value) => value,
         None => return,
     };
-    data.clothes.remove(clth.borrow().id).unwrap();
+    let id = clth.borrow().id;
+
+    // weak references may also come from an unfinished outfit.
+    if Rc::weak_count(&clth) == 0 || data.outfits.using(id).is_empty() {
+        let confirmed = InputErr::until_ok(|| {
+            input::confirm(&format!("Remove clothing {}", id))
+        });
+        if confirmed == Some(true) {
+            rm_clth(data, id).unwrap();
+        }
+        return;
+    }
+
+    println!("Removing clothing {} also removes these outfits:\n", id);
+    print_outfits_of(data, id);
+
+    match InputErr::until_ok(input::removal) {
+        Some(input::Removal::Clth) => rm_clth(data, id).unwrap(),
+        Some(input::Removal::Outfits) => {
+            rm_outfits_of(data, id);
+        },
+        None => (),
+    }
+}
+
+fn print_outfits_of(data: &Data, id: u32) {
+    for outfit in data.outfits.using(id) {
+        println!("{}\n", outfit);
+    }
+}
+
+/// Archives the clothing `id` with `status`, keeping it as a record. The
+/// outfits using it are removed.
+pub fn archive_clth(data: &mut Data, id: u32, status: Status) -> Result<(), ErrMsg> {
+    let before = data.clothes.get(id).ok_or("Clothing not found.")?.borrow().clone();
+    if !before.status.is_active() {
+        return Err("The clothing is already archived.");
+    }
+    if status.is_active() {
+        return Err("Archived clothes need a status other than 'Active'.");
+    }
+
+    let since = data.history.done.len();
+    rm_outfits_of(data, id);
+    data.clothes.archive(id, status)?;
+    let after = data.clothes.get(id).unwrap().borrow().clone();
+    data.history.record(Op::UpdateClth { before, after, new_style: false });
+    data.history.merge_since(since);
+    data.changes.push(Change::UpdateClth(id));
+    Ok(())
+}
+
+/// Records `sale`, archiving its clothing as sold on the date of the sale.
+pub fn sell_clth(data: &mut Data, sale: Sale) -> Result<(), ErrMsg> {
+    if data.sales.get(sale.clth).is_some() {
+        return Err("The clothing has already been sold.");
+    }
+    let since = data.history.done.len();
+    archive_clth(data, sale.clth, Status::Sold(sale.date))?;
+
+    let id = sale.clth;
+    data.history.record(Op::AddSale(sale.clone()));
+    data.history.merge_since(since);
+    data.sales.add(sale);
+    data.changes.push(Change::Sale(id));
+    Ok(())
+}
+
+pub fn user_sell_clth(data: &mut Data) {
+    let clothes = data.clothes.active();
+    println!("{}", clothes);
+    let id = match InputErr::until_ok(|| input::select_clth(&clothes)) {
+        Some(clth) => clth.borrow().id,
+        None => return,
+    };
+
+    let price = match InputErr::until_ok(input::price) {
+        Some(price) => price,
+        None => return,
+    };
+    let channel = match InputErr::until_ok(input::channel) {
+        Some(channel) => channel,
+        None => return,
+    };
+
+    if !data.outfits.using(id).is_empty() {
+        println!("Selling clothing {} removes these outfits:\n", id);
+        print_outfits_of(data, id);
+        let confirmed = InputErr::until_ok(|| input::confirm("Sell it anyway"));
+        if confirmed != Some(true) { return; }
+    }
+
+    let sale = Sale { clth: id, date: Local::today().naive_local(), price, channel };
+    match sell_clth(data, sale) {
+        Ok(()) => println!("Sale has been recorded.\n"),
+        Err(msg) => eprintln!("Error while recording sale: {}", msg),
+    }
+}
+
+/// Donates the clothes `ids`, all marked for donation, to `recipient` as a
+/// new batch, returning its id. The clothes are archived as donated.
+pub fn donate(data: &mut Data, recipient: &str, date: NaiveDate, ids: &[u32])
+    -> Result<u32, ErrMsg>
+{
+    if ids.is_empty() {
+        return Err("A donation must hold some clothing.");
+    }
+    if ids.iter().enumerate().any(|(index, id)| ids[..index].contains(id)) {
+        return Err("A clothing can only be donated once.");
+    }
+    for id in ids {
+        let clth = data.clothes.get(*id).ok_or("Clothing not found.")?.borrow();
+        if !clth.status.is_active() {
+            return Err("Archived clothes can't be donated.");
+        }
+        if clth.target != Target::Donation {
+            return Err("Only clothes marked for donation can be donated.");
+        }
+    }
+
+    let since = data.history.done.len();
+    for id in ids {
+        archive_clth(data, *id, Status::Donated(date))?;
+    }
+    let donation = Donation {
+        id: data.donations.request_id(),
+        recipient: String::from(recipient),
+        date,
+        clothes: ids.to_vec(),
+    };
+    let id = donation.id;
+    data.history.record(Op::AddDonation(donation.clone()));
+    data.history.merge_since(since);
+    data.donations.add(donation);
+    data.changes.push(Change::Donation(id));
+    Ok(id)
+}
+
+pub fn user_donate(data: &mut Data) {
+    let clothes = data.clothes.active().map_by_target().remove("donation").unwrap();
+    println!("{}", clothes);
+    let ids = match InputErr::until_ok(|| input::select_clth_ids(&clothes)) {
+        Some(ids) => ids,
+        None => return,
+    };
+
+    let recipient = input::read_not_empty("Enter the recipient organisation: ");
+    if ids.iter().any(|id| !data.outfits.using(*id).is_empty()) {
+        println!("Donating these clothes removes these outfits:\n");
+        for id in ids.iter() {
+            print_outfits_of(data, *id);
+        }
+        let confirmed = InputErr::until_ok(|| input::confirm("Donate them anyway"));
+        if confirmed != Some(true) { return; }
+    }
+
+    match donate(data, &recipient, Local::today().naive_local(), &ids) {
+        Ok(id) => println!("Donation {} has been recorded.\n", id),
+        Err(msg) => eprintln!("Error while recording donation: {}", msg),
+    }
+}
+
+pub fn user_print_receipt(data: &mut Data) {
+    println!("{}\n", data.donations);
+    let donation = match InputErr::until_ok(|| input::select_donation(&data.donations)) {
+        Some(donation) => donation,
+        None => return,
+    };
+    println!("{}\n", donation.receipt(&data.clothes));
+}
+
+pub fn user_archive_clth(data: &mut Data) {
+    let clothes = data.clothes.active();
+    println!("{}", clothes);
+    let id = match InputErr::until_ok(|| input::select_clth(&clothes)) {
+        Some(clth) => clth.borrow().id,
+        None => return,
+    };
+
+    if !data.outfits.using(id).is_empty() {
+        println!("Archiving clothing {} removes these outfits:\n", id);
+        print_outfits_of(data, id);
+        let confirmed = InputErr::until_ok(|| input::confirm("Archive it anyway"));
+        if confirmed != Some(true) { return; }
+    }
+
+    // sold and donated clothes are archived by recording their sale or donation.
+    match archive_clth(data, id, Status::Archived(Local::today().naive_local())) {
+        Ok(()) => println!("Clothing has been archived.\n"),
+        Err(msg) => eprintln!("Error while archiving clothing: {}", msg),
+    }
+}
+
+/// Sets (or clears) the purchase price of the clothing `id`.
+pub fn set_cost(data: &mut Data, id: u32, cost: Option<Money>) -> Result<(), ErrMsg> {
+    let clth = data.clothes.get(id).ok_or("Clothing not found.")?;
+    let before = clth.borrow().clone();
+    clth.borrow_mut().cost = cost;
+    let after = clth.borrow().clone();
+    data.history.record(Op::UpdateClth { before, after, new_style: false });
+    data.changes.push(Change::UpdateClth(id));
+    Ok(())
+}
+
+/// Counts one more wear of the clothing `id`.
+pub fn wear_clth(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    let clth = data.clothes.get(id).ok_or("Clothing not found.")?;
+    let before = clth.borrow().clone();
+    if !before.status.is_active() {
+        return Err("Archived clothes can't be worn.");
+    }
+    clth.borrow_mut().wears += 1;
+    let after = clth.borrow().clone();
+    data.history.record(Op::UpdateClth { before, after, new_style: false });
+    data.changes.push(Change::UpdateClth(id));
+    Ok(())
+}
+
+pub fn user_wear_clth(data: &mut Data) {
+    let clothes = data.clothes.active();
+    println!("{}", clothes);
+    let id = match InputErr::until_ok(|| input::select_clth(&clothes)) {
+        Some(clth) => clth.borrow().id,
+        None => return,
+    };
+
+    match wear_clth(data, id) {
+        Ok(()) => println!("Wear has been recorded.\n"),
+        Err(msg) => eprintln!("Error while recording wear: {}", msg),
+    }
+}
+
+/// Defines a new kind of clothing.
+pub fn add_kind(data: &mut Data, kind: Kind) -> Result<(), ErrMsg> {
+    let kind = data.kinds.add(kind)?;
+    data.history.record(Op::AddKind(Kind::clone(&kind)));
+    data.changes.push(Change::Kind(kind.name.clone()));
+    Ok(())
+}
+
+pub fn user_add_kind(data: &mut Data) {
+    println!("{}\n", &data.kinds);
+    let name = input::read_not_empty("Enter the name of the kind: ");
+    let slot = match InputErr::until_ok(input::slot) {
+        Some(slot) => slot,
+        None => return,
+    };
+    let sizes = match InputErr::until_ok(|| input::size_system(slot)) {
+        Some(sizes) => sizes,
+        None => return,
+    };
+
+    let mut kind = Kind::new(&name, slot);
+    kind.sizes = sizes;
+    match add_kind(data, kind) {
+        Ok(()) => println!("Kind has been added.\n"),
+        Err(msg) => eprintln!("Error while adding kind: {}", msg),
+    }
+}
+
+/// Removes every outfit using the clothing `id`, returning their ids.
+pub fn rm_outfits_of(data: &mut Data, id: u32) -> Vec<u32> {
+    let ids: Vec<u32> = data.outfits.using(id).iter().map(|outfit| outfit.id).collect();
+    let since = data.history.done.len();
+    for outfit_id in ids.iter() {
+        rm_outfit(data, *outfit_id).unwrap();
+    }
+    data.history.merge_since(since);
+    ids
 }
 
 pub fn user_update_clth(data: &mut Data) {
-    println!("{}\n", data.clothes);
-    let clth = InputErr::until_ok(|| input::select_clth(&data.clothes));
-    if let None = clth { return ;}
+    let clothes = data.clothes.active();
+    println!("{}\n", clothes);
+    let clth = InputErr::until_ok(|| input::select_clth(&clothes));
+    if clth.is_none() { return ;}
     let field = InputErr::until_ok(input::select_clth_field);
-    if let None = field { return ;}
+    if field.is_none() { return ;}
 
+    let id = clth.as_ref().unwrap().borrow().id;
+    let before = clth.as_ref().unwrap().borrow().clone();
+    let mut new_style = false;
     match field.unwrap().as_str() {
         "color" => {
             let color = InputErr::until_ok(input::color);
-            if let None = color { return ;}
+            if color.is_none() { return ;}
             clth.unwrap().borrow_mut().color = color.unwrap();
         },
         "kind" => {
@@ -164,18 +587,29 @@ pub fn user_update_clth(data: &mut Data) {
                 return;
             }
 
-            let kind = InputErr::until_ok(input::kind);
-            if let None = kind { return ;}
-            clth.borrow_mut().kind = kind.unwrap();
+            let kind = match InputErr::until_ok(|| input::kind(&data.kinds)) {
+                Some(kind) => kind,
+                None => return,
+            };
+            // a size that doesn't fit the new kind is asked again.
+            if !kind.sizes.fits(&clth.borrow().size) {
+                println!("The size {} doesn't fit a {}.", clth.borrow().size, kind);
+                let size = InputErr::until_ok(|| input::size(&kind));
+                if size.is_none() { return ;}
+                clth.borrow_mut().size = size.unwrap();
+            }
+            clth.borrow_mut().kind = kind;
         },
         "size" => {
-            let size = InputErr::until_ok(input::size);
-            if let None = size { return ;}
-            clth.unwrap().borrow_mut().size = size.unwrap();
+            let clth = clth.unwrap();
+            let kind = Rc::clone(&clth.borrow().kind);
+            let size = InputErr::until_ok(|| input::size(&kind));
+            if size.is_none() { return ;}
+            clth.borrow_mut().size = size.unwrap();
         },
         "sex" => {
             let sex = InputErr::until_ok(input::sex);
-            if let None = sex { return ;}
+            if sex.is_none() { return ;}
             clth.unwrap().borrow_mut().sex = sex.unwrap();
         },
         "target" => {
@@ -184,8 +618,8 @@ pub fn user_update_clth(data: &mut Data) {
                 None => return,
             };
 
-            let target = InputErr::until_ok(|| input::target(price));
-            if let None = target { return ;}
+            let target = InputErr::until_ok(|| input::target(price.clone()));
+            if target.is_none() { return ;}
             clth.unwrap().borrow_mut().target = target.unwrap();
         },
         "style" => {
@@ -202,15 +636,27 @@ pub fn user_update_clth(data: &mut Data) {
                 None => return,
             };
 
+            new_style = data.styles.get(&stl_name).is_none();
             clth.borrow_mut().style = data.styles.get_or_add(&stl_name);
         },
+        "purchase price" => {
+            let cost = match InputErr::until_ok(input::cost) {
+                Some(cost) => cost,
+                None => return,
+            };
+            clth.unwrap().borrow_mut().cost = cost;
+        },
         value => panic!("Expecting a clothing field, found: '{}'.", value)
     }
+    let after = data.clothes.get(id).unwrap().borrow().clone();
+    data.history.record(Op::UpdateClth { before, after, new_style });
+    data.changes.push(Change::UpdateClth(id));
 }
 
 pub fn user_add_outfit(data: &mut Data) {
     let cache = match data.cache.outfit.take() {
         Some(value) => {
+            data.changes.push(Change::Draft);
             let use_cache = InputErr::until_ok(|| {
                 input::confirm("Restore last session")
             }).unwrap();
@@ -219,33 +665,60 @@ pub fn user_add_outfit(data: &mut Data) {
         },
         None => OutfitBuffer::new()
     };
+    user_fill_outfit(data, cache);
+}
 
+/// Asks for the clothes missing on `cache` and adds the outfit. If the user
+/// gives up, the buffer is kept as a draft.
+pub fn user_fill_outfit(data: &mut Data, cache: OutfitBuffer) {
     let cache_res = InputErr::log_until_ok(cache, |log| {
-        fill_outfit_buffer(log, &data.clothes)
+        fill_outfit_buffer(log, &data.clothes.active())
     });
 
-    let cache = match cache_res {
+    let mut cache = match cache_res {
         Ok(buffer) => buffer,
         Err((buffer, _)) => {
             data.cache.outfit = Some(buffer);
+            data.changes.push(Change::Draft);
             return;
         },
     };
+    pick_extras(&mut cache, &data.clothes.active());
 
-    let outfit = cache.to_outfit(data.outfits.request_id());
-
-    let result = match outfit {
-        Ok(set) => data.outfits.add(set),
+    let draft = OutfitBuffer { clothes: cache.clothes.clone() };
+    match add_outfit(data, cache) {
+        Ok(_) => println!("Oufit has been added.\n"),
         Err(msg) => {
-            eprintln!("Error while creating outfit: {}", msg);
-            return ;
+            eprintln!("Error while adding outfit: {}", msg);
+            data.cache.outfit = Some(draft);
+            data.changes.push(Change::Draft);
         },
-    };
+    }
+}
 
-    match result {
-        Some(err) => eprintln!("Error while adding outfit: {}", err),
-        None => println!("Oufit has been added.\n")
+/// Builds an outfit from `buffer` and adds it to `data`, returning its id.
+pub fn add_outfit(data: &mut Data, buffer: OutfitBuffer) -> Result<u32, ErrMsg> {
+    let outfit = buffer.to_outfit(data.outfits.request_id())?;
+    if outfit.to_clothes().list.iter().any(|clth| !clth.borrow().status.is_active()) {
+        return Err("Archived clothes can't be in an outfit.");
     }
+    let id = outfit.id;
+    let ids = outfit.to_ids();
+    match data.outfits.add(outfit) {
+        Some(msg) => Err(msg),
+        None => {
+            data.history.record(Op::AddOutfit((id, ids)));
+            data.changes.push(Change::AddOutfit(id));
+            Ok(id)
+        },
+    }
+}
+
+pub fn rm_outfit(data: &mut Data, id: u32) -> Result<(), ErrMsg> {
+    let outfit = data.outfits.remove(id)?;
+    data.history.record(Op::RemoveOutfit((id, outfit.to_ids())));
+    data.changes.push(Change::RemoveOutfit(id));
+    Ok(())
 }
 
 pub fn user_rm_outfit(data: &mut Data) {
@@ -254,7 +727,95 @@ pub fn user_rm_outfit(data: &mut Data) {
         None => return,
     };
 
-    data.outfits.remove(outfit.id).unwrap();
+    let id = outfit.id;
+    rm_outfit(data, id).unwrap();
+}
+
+/// Lets the user pick a quarantined record to repair, retry or discard.
+pub fn user_repair_quarantined(data: &mut Data) {
+    if data.quarantine.is_empty() {
+        println!("No quarantined records.\n");
+        return;
+    }
+    println!("{}\n", &data.quarantine);
+
+    let count = data.quarantine.len();
+    let index = InputErr::until_ok(|| {
+        let input = input::read_not_empty("Select a record: ").to_lowercase();
+        match input.as_str() {
+            "exit" => Err(InputErr::user_abort()),
+            input => match input.parse::<usize>() {
+                Ok(index) if index < count => Ok(index),
+                _ => Err(InputErr::wrong("Invalid record!")),
+            },
+        }
+    });
+    let index = match index {
+        Some(index) => index,
+        None => return,
+    };
+
+    let actions = ["Set a field", "Remove a field", "Retry", "Discard", "Exit"];
+    data.changes.push(Change::Quarantine);
+    loop {
+        println!("{}", data.quarantine.list[index]);
+        let menu = input::menu_from_vec("repair menu", &actions);
+        match Runner::new(menu).run("Select an action: ").unwrap() {
+            0 => {
+                let line = input::read_not_empty("Enter 'field = value': ");
+                if let Err(msg) = data.quarantine.list[index].edit(&line) {
+                    eprintln!("{}", msg);
+                }
+            },
+            1 => {
+                let key = input::read_not_empty("Enter a field name: ");
+                if data.quarantine.list[index].chunk.fields.remove(&key).is_none() {
+                    eprintln!("No such field.");
+                }
+            },
+            2 => match quarantine::restore(data, index) {
+                Ok(_) => {
+                    println!("Record has been restored.\n");
+                    return;
+                },
+                Err(_) => eprintln!("The record is still invalid."),
+            },
+            3 => {
+                if InputErr::until_ok(|| input::confirm("Discard it")) == Some(true) {
+                    quarantine::discard(data, index).unwrap();
+                    println!("Record has been discarded.\n");
+                    return;
+                }
+            },
+            _ => return,
+        }
+    }
+}
+
+/// Offers to finish the drafts left unfinished on the last session. Drafts
+/// that are not finished now are kept for later.
+pub fn user_restore_drafts(data: &mut Data) {
+    if data.cache.clth.is_some() {
+        let restore = InputErr::until_ok(|| {
+            input::confirm("Finish the clothing left unfinished on the last session")
+        });
+        if restore == Some(true) {
+            let buffer = data.cache.clth.take().unwrap();
+            data.changes.push(Change::Draft);
+            user_fill_clth(data, buffer);
+        }
+    }
+
+    if data.cache.outfit.is_some() {
+        let restore = InputErr::until_ok(|| {
+            input::confirm("Finish the outfit left unfinished on the last session")
+        });
+        if restore == Some(true) {
+            let buffer = data.cache.outfit.take().unwrap();
+            data.changes.push(Change::Draft);
+            user_fill_outfit(data, buffer);
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -263,19 +824,42 @@ pub enum Event {
     RemoveClth,
     ListClths,
     UpdateClth,
+    ArchiveClth,
+    WearClth,
+    ListArchived,
+    AddKind,
+    ListKinds,
     AddOutfit,
     RemoveOutfit,
     ListOutfits,
+    SellClth,
+    ListSales,
+    SalesReport,
+    CostReport,
+    Donate,
+    ListDonations,
+    PrintReceipt,
+    ListQuarantine,
+    RepairQuarantined,
+    Undo,
+    Redo,
     Back,
     Quit,
 }
 
-pub fn run(data: &mut Data) {
+/// Runs the interactive menu. Every change is persisted to `store` as soon
+/// as the action that made it is done.
+pub fn run(data: &mut Data, store: &mut dyn Store) {
     let mut clth_menu = Menu::new("Clothes");
     clth_menu.add_action(Act::new("Add clothing", Event::AddClth));
     clth_menu.add_action(Act::new("Remove clothing", Event::RemoveClth));
     clth_menu.add_action(Act::new("Update clothing", Event::UpdateClth));
+    clth_menu.add_action(Act::new("Archive clothing", Event::ArchiveClth));
+    clth_menu.add_action(Act::new("Record a wear", Event::WearClth));
     clth_menu.add_action(Act::new("List clothes", Event::ListClths));
+    clth_menu.add_action(Act::new("List archived clothes", Event::ListArchived));
+    clth_menu.add_action(Act::new("Add kind", Event::AddKind));
+    clth_menu.add_action(Act::new("List kinds", Event::ListKinds));
     clth_menu.add_action(Act::new("Back", Event::Back));
 
     let mut outfit_menu = Menu::new("Outfits");
@@ -284,29 +868,81 @@ pub fn run(data: &mut Data) {
     outfit_menu.add_action(Act::new("List outfits", Event::ListOutfits));
     outfit_menu.add_action(Act::new("Back", Event::Back));
 
+    let mut sales_menu = Menu::new("Sales");
+    sales_menu.add_action(Act::new("Sell clothing", Event::SellClth));
+    sales_menu.add_action(Act::new("List sales", Event::ListSales));
+    sales_menu.add_action(Act::new("Sales report", Event::SalesReport));
+    sales_menu.add_action(Act::new("Cost report", Event::CostReport));
+    sales_menu.add_action(Act::new("Back", Event::Back));
+
+    let mut donation_menu = Menu::new("Donations");
+    donation_menu.add_action(Act::new("Donate clothes", Event::Donate));
+    donation_menu.add_action(Act::new("List donations", Event::ListDonations));
+    donation_menu.add_action(Act::new("Print receipt", Event::PrintReceipt));
+    donation_menu.add_action(Act::new("Back", Event::Back));
+
+    let mut quarantine_menu = Menu::new("Quarantine");
+    quarantine_menu.add_action(Act::new("List records", Event::ListQuarantine));
+    quarantine_menu.add_action(Act::new("Repair record", Event::RepairQuarantined));
+    quarantine_menu.add_action(Act::new("Back", Event::Back));
+
     let mut menu = Menu::new("root");
     menu.add_submenu(clth_menu);
     menu.add_submenu(outfit_menu);
+    menu.add_submenu(sales_menu);
+    menu.add_submenu(donation_menu);
+    menu.add_submenu(quarantine_menu);
+    menu.add_action(Act::new("Undo", Event::Undo));
+    menu.add_action(Act::new("Redo", Event::Redo));
     menu.add_action(Act::new("Quit", Event::Quit));
 
     let mut runner = Runner::new(menu);
 
+    user_restore_drafts(data);
+    if let Err(err) = store.commit(data) {
+        eprintln!("Error while saving: {}", err);
+    }
+
     loop {
         if let Some(act) = runner.run("> ") {
             match act {
                 Event::AddClth => user_add_clth(data),
-                Event::RemoveClth => {
-                    user_rm_clth(data);
-                    data.outfits.clean();
-                },
-                Event::ListClths => println!("{}\n", &data.clothes),
+                Event::RemoveClth => user_rm_clth(data),
+                Event::ListClths => println!("{}\n", &data.clothes.active()),
                 Event::UpdateClth => user_update_clth(data),
+                Event::ArchiveClth => user_archive_clth(data),
+                Event::WearClth => user_wear_clth(data),
+                Event::ListArchived => println!("{}\n", &data.clothes.archived()),
+                Event::AddKind => user_add_kind(data),
+                Event::ListKinds => println!("{}\n", &data.kinds),
                 Event::AddOutfit => user_add_outfit(data),
                 Event::RemoveOutfit => user_rm_outfit(data),
                 Event::ListOutfits => println!("{}\n", &data.outfits),
+                Event::SellClth => user_sell_clth(data),
+                Event::ListSales => println!("{}\n", &data.sales),
+                Event::SalesReport => println!("{}\n", sales::Report::new(data)),
+                Event::CostReport => println!("{}\n", costs::Report::new(data)),
+                Event::Donate => user_donate(data),
+                Event::ListDonations => println!("{}\n", &data.donations),
+                Event::PrintReceipt => user_print_receipt(data),
+                Event::ListQuarantine => println!("{}\n", &data.quarantine),
+                Event::RepairQuarantined => user_repair_quarantined(data),
+                Event::Undo => match history::undo(data) {
+                    Ok(op) => println!("Undone: {}.\n", op),
+                    Err(msg) => eprintln!("{}", msg),
+                },
+                Event::Redo => match history::redo(data) {
+                    Ok(op) => println!("Redone: {}.\n", op),
+                    Err(msg) => eprintln!("{}", msg),
+                },
                 Event::Back => runner.back().unwrap(),
                 Event::Quit => break,
             }
         }
+
+        if let Err(err) = store.commit(data) {
+            eprintln!("Error while saving: {}\nThe changes will be saved again \
+                on the next action.", err);
+        }
     }
 }
@@ -0,0 +1,98 @@
+//! Advisory lock keeping two recloset processes from using the same closet.
+//!
+//! The lock is taken on a `.lock` file next to the data file, which holds
+//! the pid of the process owning it. It is released when the `Lock` is
+//! dropped or the process exits.
+
+use std::fmt::Display;
+use std::fs::{ self, File, OpenOptions, TryLockError };
+use std::io::{ self, Read, Seek, Write };
+use std::path::{ Path, PathBuf };
+use std::process;
+
+use crate::storage;
+
+pub fn lock_path(path: &Path) -> PathBuf {
+    storage::sibling_path(path, ".lock")
+}
+
+#[derive(Debug)]
+pub enum LockErr {
+    /// Another process holds the lock; holds its pid, if known.
+    Held(Option<u32>),
+    Io(io::Error),
+}
+
+impl Display for LockErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockErr::Held(Some(pid)) => {
+                write!(f, "Another recloset (pid {}) is using this closet.", pid)
+            },
+            LockErr::Held(None) => write!(f, "Another recloset is using this closet."),
+            LockErr::Io(err) => write!(f, "Can't lock the closet: {}", err),
+        }
+    }
+}
+
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Locks the closet whose data file is at `path`, without waiting.
+    pub fn acquire(path: &Path) -> Result<Lock, LockErr> {
+        let path = lock_path(path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(LockErr::Io)?;
+        }
+
+        // never truncated on open: the pid of the owner must survive.
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(LockErr::Io)?;
+
+        match file.try_lock() {
+            Ok(_) => (),
+            Err(TryLockError::WouldBlock) => {
+                let mut owner = String::new();
+                file.read_to_string(&mut owner).ok();
+                return Err(LockErr::Held(owner.trim().parse().ok()));
+            },
+            Err(TryLockError::Error(err)) => return Err(LockErr::Io(err)),
+        }
+
+        file.set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| write!(file, "{}", process::id()))
+            .map_err(LockErr::Io)?;
+        Ok(Lock { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::storage::tests::tmp_dir;
+
+    #[test]
+    fn lock_once() {
+        let dir = tmp_dir("lock");
+        let path = dir.join("data.toml");
+
+        let lock = Lock::acquire(&path).unwrap();
+        match Lock::acquire(&path) {
+            Err(LockErr::Held(pid)) => assert_eq!(Some(process::id()), pid),
+            _ => panic!("The closet must be locked."),
+        }
+
+        drop(lock);
+        assert!(Lock::acquire(&path).is_ok());
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,46 +1,123 @@
-use recloset::storage::FileData;
-use std::fs::{ self, File };
+use recloset::storage::{ self, Source };
+use recloset::store::{ self, Backend, Change, ReadOnlyStore };
+use recloset::lock::{ Lock, LockErr };
+use recloset::cli::{ self, CliErr };
+use recloset::money;
+use std::env;
 use std::process;
-use std::io::Write;
-
-const DATAFILE: &str = "data.toml";
-const DATAPATH: &str = concat!(env!("HOME"), "/.config/recloset");
 
 fn main() {
-    let filedir = format!("{}/{}", DATAPATH, DATAFILE);
-    let file_content = match fs::read_to_string(&filedir) {
-        Ok(content) => content,
-        Err(_) => {
-            fs::create_dir_all(DATAPATH).unwrap();
-            File::create(&filedir).unwrap();
-            String::from("")
-        },
-    };
-
-    let fdata = match FileData::from(&file_content) {
-        Ok(data) => data,
+    let args: Vec<String> = env::args().skip(1).collect();
+    let opts = match cli::parse_opts(&args) {
+        Ok(opts) => opts,
         Err(err) => {
-            eprintln!("{}:\n  {}", filedir, err);
-            process::exit(1);
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            process::exit(err.code);
         }
     };
 
-    let mut data = match fdata.to_data() {
-        Ok(data) => data,
+    if let Some(style) = opts.money {
+        money::set_style(style);
+    }
+
+    let path = storage::data_path(opts.data.as_deref(), |key| env::var_os(key));
+    let filedir = match path {
+        Ok(path) => path,
         Err(msg) => {
-            eprintln!("{}:\n  Data error: {}", filedir, msg);
-            process::exit(1);
+            eprintln!("{}", msg);
+            process::exit(cli::EXIT_USAGE);
         }
     };
+    let filename = filedir.display();
 
-    recloset::run(&mut data);
+    // held until the process exits.
+    let lock = match opts.read_only {
+        true => None,
+        false => match Lock::acquire(&filedir) {
+            Ok(lock) => Some(lock),
+            Err(err @ LockErr::Held(_)) => {
+                eprintln!("{} {} is open read-only.", err, filename);
+                None
+            },
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(cli::EXIT_DATA);
+            },
+        },
+    };
 
-    let mut file = match File::create(&filedir) {
-        Ok(file) => file,
+    let backend = opts.store.unwrap_or_else(|| Backend::of(&filedir));
+    let mut store = match store::open(backend, &filedir) {
+        Ok(store) if lock.is_none() => Box::new(ReadOnlyStore::new(store)),
+        Ok(store) => store,
         Err(err) => {
-            eprintln!("Error while opening {} for writing: {}", &filedir, err);
-            process::exit(1);
+            eprintln!("{}", err);
+            process::exit(cli::EXIT_DATA);
         }
     };
-    file.write_all(data.to_toml().as_bytes()).unwrap();
+
+    let loaded = match store.load(opts.lenient) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            eprintln!("{}:\n  {}", filename, err);
+            eprintln!("Run with --lenient to load the valid records and \
+                quarantine the others.");
+            process::exit(cli::EXIT_DATA);
+        }
+    };
+
+    if let Source::Backup { err } = &loaded.source {
+        eprintln!("{}:\n  {}", filename, err);
+        let backup = storage::backup_path(&filedir);
+        // a read-only store leaves the damaged file for whoever holds the lock.
+        match store.restore_backup() {
+            Ok(Some(moved_to)) => eprintln!("The backup {} has been loaded instead; the \
+                damaged file was moved to {}.", backup.display(), moved_to.display()),
+            Ok(None) => eprintln!("The backup {} has been loaded instead; the damaged \
+                file was left as it is.", backup.display()),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(cli::EXIT_DATA);
+            },
+        }
+    }
+    if !loaded.diagnostics.is_empty() {
+        eprintln!("{}:\n  {}", filename,
+            loaded.diagnostics.to_string().replace('\n', "\n  "));
+    }
+    let mut data = loaded.data;
+    // a lenient load that quarantined records must write them back.
+    if loaded.diagnostics.has_errors() {
+        data.changes.push(Change::All);
+    }
+    if !data.quarantine.is_empty() {
+        eprintln!("{} record(s) are quarantined; repair them from the Quarantine \
+            menu or with 'recloset quarantine'.",
+            data.quarantine.len());
+    }
+
+    match opts.command {
+        // refused before running, so that nothing is reported as done.
+        Some(cmd) if lock.is_none() && cmd.mutates() => {
+            eprintln!("{} is open read-only; this command would change it.", filename);
+            process::exit(cli::EXIT_REJECTED);
+        },
+        Some(cmd) => {
+            if let Err(CliErr { code, msg }) = cli::exec(cmd, &mut data) {
+                eprintln!("{}", msg);
+                process::exit(code);
+            }
+        },
+        None => recloset::run(&mut data, store.as_mut()),
+    }
+
+    // a read-only closet is never saved, not even what loading it changed
+    // (quarantined records, an older format).
+    if lock.is_none() {
+        return;
+    }
+    if let Err(err) = store.commit(&mut data) {
+        eprintln!("Error while saving {}: {}", filename, err);
+        process::exit(cli::EXIT_DATA);
+    }
 }
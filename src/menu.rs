@@ -74,7 +74,7 @@ impl<T: Clone> Runner<T> {
 
         let mut input = String::new();
 
-        if let Err(_) = io::stdin().read_line(&mut input) {
+        if io::stdin().read_line(&mut input).is_err() {
             return Err("Failed to read the input!");
         }
 
@@ -0,0 +1,337 @@
+//! Amounts of money in any currency.
+//!
+//! A `Money` is an amount in cents (the minor unit) of its `Currency`, an
+//! ISO 4217 code. Amounts are parsed the way people type them, with the
+//! currency as a symbol or a code and either a dot or a comma as decimal
+//! separator (`$20.75`, `€20,75`, `R$ 20,75`, `20.75 EUR`), and are stored
+//! in the `20.75 EUR` form whatever the display style set with `set_style`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use crate::closet::ErrMsg;
+
+/// Currency of the amounts given without one.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// The largest amount, in cents, that can be stored: both the data file and
+/// SQLite keep integers as signed 64-bit numbers.
+pub const MAX_CENTS: u64 = i64::MAX as u64;
+
+/// Symbol and decimal separator of the currencies known by their symbol.
+/// `R$` comes before `$` so that it is matched first.
+const SYMBOLS: [(&str, &str, char); 4] = [
+    ("BRL", "R$ ", ','),
+    ("USD", "$", '.'),
+    ("EUR", "€", ','),
+    ("GBP", "£", '.'),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Currency(String);
+
+impl Currency {
+    pub fn new(code: &str) -> Result<Currency, ErrMsg> {
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+            return Err("Invalid currency.");
+        }
+        Ok(Currency(code.to_uppercase()))
+    }
+
+    /// Returns the currency written as `symbol`, like `€`.
+    pub fn from_symbol(symbol: &str) -> Option<Currency> {
+        SYMBOLS.iter()
+            .find(|(_, known, _)| known.trim() == symbol.trim())
+            .map(|(code, _, _)| Currency(String::from(*code)))
+    }
+
+    pub fn code(&self) -> &str {
+        &self.0
+    }
+
+    fn known(&self) -> Option<(&'static str, char)> {
+        SYMBOLS.iter()
+            .find(|(code, _, _)| *code == self.0)
+            .map(|(_, symbol, decimal)| (*symbol, *decimal))
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency(String::from(DEFAULT_CURRENCY))
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// How amounts are displayed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    /// With the symbol and decimal separator of the currency (`R$ 20,75`),
+    /// falling back to `Code` for currencies without a known symbol.
+    Symbol,
+    /// With the ISO code (`20.75 BRL`).
+    Code,
+}
+
+impl FromStr for Style {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Style, ErrMsg> {
+        match value.to_lowercase().as_str() {
+            "symbol" => Ok(Style::Symbol),
+            "code" => Ok(Style::Code),
+            _ => Err("Expected 'symbol' or 'code'."),
+        }
+    }
+}
+
+static CODE_STYLE: AtomicBool = AtomicBool::new(false);
+
+/// Sets how `Money` is displayed for the rest of the process.
+pub fn set_style(style: Style) {
+    CODE_STYLE.store(style == Style::Code, Ordering::Relaxed);
+}
+
+pub fn style() -> Style {
+    match CODE_STYLE.load(Ordering::Relaxed) {
+        true => Style::Code,
+        false => Style::Symbol,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Money {
+    pub cents: u64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(cents: u64, currency: Currency) -> Money {
+        Money { cents, currency }
+    }
+
+    /// An amount of the default currency.
+    pub fn usd(cents: u64) -> Money {
+        Money::new(cents, Currency::default())
+    }
+
+    /// The amount of cents as stored. Amounts are read and parsed within
+    /// `MAX_CENTS`, so they always fit.
+    pub fn stored_cents(&self) -> i64 {
+        i64::try_from(self.cents).expect("Amounts never exceed MAX_CENTS.")
+    }
+
+    /// Formats the amount the way it is stored, like `20.75 EUR`.
+    pub fn to_code(&self) -> String {
+        self.format(Style::Code)
+    }
+
+    pub fn format(&self, style: Style) -> String {
+        let (units, cents) = (self.cents / 100, self.cents % 100);
+        match (style, self.currency.known()) {
+            (Style::Symbol, Some((symbol, decimal))) => {
+                format!("{}{}{}{:02}", symbol, units, decimal, cents)
+            },
+            _ => format!("{}.{:02} {}", units, cents, self.currency),
+        }
+    }
+}
+
+/// Splits the currency, as a symbol or a code before or after the amount,
+/// from `text`.
+fn split_currency(text: &str) -> Result<(Option<Currency>, &str), ErrMsg> {
+    for (code, symbol, _) in SYMBOLS.iter() {
+        let symbol = symbol.trim();
+        if let Some(amount) = text.strip_prefix(symbol).or_else(|| text.strip_suffix(symbol)) {
+            return Ok((Some(Currency(String::from(*code))), amount.trim()));
+        }
+    }
+
+    let is_amount = |c: char| c.is_ascii_digit() || c == '.' || c == ',' || c == '-';
+    let start = text.find(is_amount).unwrap_or(text.len());
+    let end = text.rfind(is_amount).map_or(start, |index| index + 1);
+    let code = match (text[..start].trim(), text[end..].trim()) {
+        ("", "") => return Ok((None, text)),
+        (code, "") | ("", code) => code,
+        _ => return Err("Invalid price."),
+    };
+    Ok((Some(Currency::new(code)?), text[start..end].trim()))
+}
+
+/// Reads an amount like `20.75` or `20,75` as cents, exactly: amounts with
+/// more than two decimals are rejected rather than rounded, and amounts above
+/// `MAX_CENTS` since they couldn't be stored.
+fn parse_cents(amount: &str) -> Result<u64, ErrMsg> {
+    if amount.starts_with('-') {
+        return Err("Prices can't be negative.");
+    }
+    let (units, decimals) = match amount.split_once(['.', ',']) {
+        Some((units, decimals)) => (units, decimals),
+        None => (amount, ""),
+    };
+    let is_number = |text: &str| text.chars().all(|c| c.is_ascii_digit());
+    if (units.is_empty() && decimals.is_empty()) || !is_number(units) || !is_number(decimals) {
+        return Err("Invalid price.");
+    }
+    if decimals.len() > 2 {
+        return Err("Prices have at most two decimals.");
+    }
+
+    let too_large = "The amount is too large.";
+    let units: u64 = match units {
+        "" => 0,
+        units => units.parse().map_err(|_| too_large)?,
+    };
+    let cents: u64 = format!("{:0<2}", decimals).parse().unwrap();
+    units.checked_mul(100)
+        .and_then(|units| units.checked_add(cents))
+        .filter(|cents| *cents <= MAX_CENTS)
+        .ok_or(too_large)
+}
+
+impl FromStr for Money {
+    type Err = ErrMsg;
+
+    /// Parses an amount, in `DEFAULT_CURRENCY` unless a currency is given.
+    fn from_str(value: &str) -> Result<Money, ErrMsg> {
+        let (currency, amount) = split_currency(value.trim())?;
+        let cents = parse_cents(amount)?;
+        Ok(Money::new(cents, currency.unwrap_or_default()))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(style()))
+    }
+}
+
+/// A sum of amounts, in cents per currency.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Amounts(pub BTreeMap<Currency, u64>);
+
+impl Amounts {
+    pub fn new() -> Amounts {
+        Amounts(BTreeMap::new())
+    }
+
+    pub fn add(&mut self, money: &Money) {
+        let sum = self.0.entry(money.currency.clone()).or_default();
+        *sum = sum.saturating_add(money.cents);
+    }
+
+    /// Returns the sum of the amounts in `currency`.
+    pub fn get(&self, currency: &Currency) -> Money {
+        Money::new(self.0.get(currency).copied().unwrap_or(0), currency.clone())
+    }
+}
+
+impl fmt::Display for Amounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "{}", Money::usd(0));
+        }
+        let amounts: Vec<String> = self.0
+            .iter()
+            .map(|(currency, cents)| Money::new(*cents, currency.clone()).to_string())
+            .collect();
+        write!(f, "{}", amounts.join(" + "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::read_data;
+
+    fn money(cents: u64, code: &str) -> Money {
+        Money::new(cents, Currency::new(code).unwrap())
+    }
+
+    #[test]
+    fn parse_money() {
+        assert_eq!(money(2075, "BRL"), Money::from_str("R$ 20,75").unwrap());
+        assert_eq!(money(2075, "EUR"), Money::from_str("€20.75").unwrap());
+        assert_eq!(money(2075, "EUR"), Money::from_str("20,75€").unwrap());
+        assert_eq!(money(2075, "USD"), Money::from_str("$20.75").unwrap());
+        assert_eq!(money(1000, "CHF"), Money::from_str("10 chf").unwrap());
+        assert_eq!(money(1000, "CHF"), Money::from_str("CHF 10").unwrap());
+        assert_eq!(money(1000, DEFAULT_CURRENCY), Money::from_str("10").unwrap());
+        assert_eq!(money(1999, "USD"), Money::from_str("19.99").unwrap());
+        assert_eq!(money(50, "USD"), Money::from_str(".5").unwrap());
+        assert_eq!(money(MAX_CENTS, "USD"), Money::from_str("92233720368547758.07").unwrap());
+        for bad in ["", "$", ".", "10 EURO", "EUR 10 USD", "ten", "1.2.3", "1 000", "+1"] {
+            assert!(Money::from_str(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn explain_bad_amounts() {
+        assert_eq!(Err("Prices can't be negative."), Money::from_str("-1"));
+        assert_eq!(Err("Prices can't be negative."), Money::from_str("€-0,50"));
+        assert_eq!(Err("Prices have at most two decimals."), Money::from_str("19.999"));
+        assert_eq!(Err("The amount is too large."), Money::from_str("92233720368547758.08"));
+        assert_eq!(Err("The amount is too large."), Money::from_str("184467440737095516.15"));
+        assert_eq!(Err("The amount is too large."), Money::from_str("99999999999999999999999"));
+    }
+
+    quickcheck::quickcheck! {
+        fn code_round_trip(cents: u64) -> bool {
+            let value = money(cents % (MAX_CENTS + 1), "CHF");
+            Money::from_str(&value.to_code()) == Ok(value)
+        }
+
+        fn symbol_round_trip(cents: u64, index: usize) -> bool {
+            let (code, _, _) = SYMBOLS[index % SYMBOLS.len()];
+            let value = money(cents % (MAX_CENTS + 1), code);
+            Money::from_str(&value.format(Style::Symbol)) == Ok(value)
+        }
+
+        fn storage_round_trip(cents: u64) -> bool {
+            let mut data = crate::storage::tests::closet();
+            let value = Money::from_str(&money(cents % (MAX_CENTS + 1), "CHF").to_code()).unwrap();
+            crate::set_cost(&mut data, 0, Some(value.clone())).unwrap();
+            let again = read_data(&data.to_toml(), false).unwrap().0;
+            let cost = again.clothes.get(0).unwrap().borrow().cost.clone();
+            cost == Some(value)
+        }
+    }
+
+    #[test]
+    fn round_trip_small_amounts() {
+        // every cent up to a hundred, in each known currency.
+        for cents in 0..=10_000 {
+            for (code, _, _) in SYMBOLS {
+                let value = money(cents, code);
+                assert_eq!(Ok(&value), Money::from_str(&value.to_string()).as_ref());
+            }
+        }
+    }
+
+    #[test]
+    fn format_money() {
+        assert_eq!("R$ 20,75", money(2075, "BRL").format(Style::Symbol));
+        assert_eq!("€5,05", money(505, "EUR").format(Style::Symbol));
+        assert_eq!("10.00 CHF", money(1000, "CHF").format(Style::Symbol));
+        assert_eq!("20.75 BRL", money(2075, "BRL").format(Style::Code));
+        for text in ["R$ 20,75", "€5,05", "$0.99", "12.34 CHF"] {
+            let value = Money::from_str(text).unwrap();
+            assert_eq!(value, Money::from_str(&value.to_code()).unwrap());
+            assert_eq!(text, value.format(Style::Symbol));
+        }
+
+        let mut sum = Amounts::new();
+        sum.add(&money(1000, "USD"));
+        sum.add(&money(250, "EUR"));
+        sum.add(&money(50, "USD"));
+        assert_eq!("€2,50 + $10.50", sum.to_string());
+        assert_eq!(money(1050, "USD"), sum.get(&Currency::default()));
+    }
+}
@@ -0,0 +1,408 @@
+//! Records set aside by a lenient load, kept as `[[quarantine]]` entries of
+//! the data file until they are repaired and restored, or discarded.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+use crate::Data;
+use crate::store::Change;
+use crate::storage::{ self, DataChunk, DataHeader, Diagnostic, Diagnostics, ParseError };
+use crate::storage::{ Severity, Value };
+
+#[derive(Clone)]
+pub struct QuarantinedChunk {
+    pub chunk: DataChunk,
+    /// Why the record was rejected the last time it was validated.
+    pub reasons: Vec<String>,
+}
+
+impl QuarantinedChunk {
+    /// Sets the fields of a `key = value` TOML assignment, e.g.
+    /// `kind = "chest"`.
+    pub fn edit(&mut self, assignment: &str) -> Result<(), String> {
+        let doc = assignment
+            .parse::<toml::Value>()
+            .map_err(|_| String::from("Expected a 'key = value' assignment."))?;
+
+        match doc {
+            toml::Value::Table(table) if !table.is_empty() => {
+                for (key, value) in table {
+                    self.chunk.fields.insert(key, Value::from_toml(value));
+                }
+                Ok(())
+            },
+            _ => Err(String::from("Expected a 'key = value' assignment.")),
+        }
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        let mut keys: Vec<&String> = self.chunk.fields.keys().collect();
+        keys.sort();
+
+        let mut fields = toml::value::Table::new();
+        for key in keys {
+            fields.insert(key.clone(), self.chunk.fields[key].to_toml());
+        }
+
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("section"), toml::Value::from(self.chunk.header.name()));
+        table.insert(String::from("reasons"), toml::Value::from(self.reasons.clone()));
+        table.insert(String::from("fields"), toml::Value::Table(fields));
+        toml::Value::Table(table)
+    }
+}
+
+impl Display for QuarantinedChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut fields: Vec<String> = self.chunk.fields
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        fields.sort();
+
+        write!(f, "[{}] {}", self.chunk.header.name(), fields.join(", "))?;
+        for reason in self.reasons.iter() {
+            write!(f, "\n    - {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Quarantine {
+    pub list: Vec<QuarantinedChunk>,
+}
+
+impl Quarantine {
+    pub fn new() -> Quarantine {
+        Quarantine { list: Vec::new() }
+    }
+
+    /// Quarantines `chunk`, rejected because of the errors of `diags`.
+    pub fn add(&mut self, chunk: DataChunk, diags: &[Diagnostic]) {
+        self.list.push(QuarantinedChunk { chunk, reasons: reasons(diags) });
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Result<&mut QuarantinedChunk, String> {
+        let len = self.list.len();
+        self.list.get_mut(index).ok_or_else(|| {
+            format!("No quarantined record {} (there are {}).", index, len)
+        })
+    }
+
+    /// Discards a quarantined record for good.
+    pub fn remove(&mut self, index: usize) -> Result<QuarantinedChunk, String> {
+        self.get_mut(index)?;
+        Ok(self.list.remove(index))
+    }
+
+    /// Ids of the quarantined clothes, which records of other sections may
+    /// still reference.
+    pub fn clth_ids(&self) -> Vec<u32> {
+        self.list
+            .iter()
+            .filter(|item| matches!(item.chunk.header, DataHeader::Clth))
+            .filter_map(|item| match item.chunk.fields.get("id") {
+                Some(Value::Num(id)) => u32::try_from(*id).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(self.list.iter().map(|item| item.to_toml()).collect())
+    }
+
+    /// Reads the `quarantine` entries of a data file. `text` is the source of
+    /// the document, used to locate the entries; it may be empty.
+    pub fn from_toml(value: toml::Value, text: &str) -> Result<Quarantine, ParseError> {
+        let items = match value {
+            toml::Value::Array(items) => items,
+            table @ toml::Value::Table(_) => vec![table],
+            _ => return Err(ParseError::new(0, String::from("'quarantine' must be a table."))),
+        };
+
+        let lines = storage::header_lines(text, "quarantine");
+        let mut quarantine = Quarantine::new();
+        for (index, item) in items.into_iter().enumerate() {
+            let line = lines.get(index).copied().unwrap_or(0);
+            let err = |msg: &str| ParseError::new(line, format!("Quarantine entry: {}", msg));
+
+            let mut table = match item {
+                toml::Value::Table(table) => table,
+                _ => return Err(err("expected a table.")),
+            };
+            let header = match table.remove("section").as_ref().and_then(|v| v.as_str()) {
+                Some("kind") => DataHeader::Kind,
+                Some("clth") => DataHeader::Clth,
+                Some("outfit") => DataHeader::Outfit,
+                Some("sale") => DataHeader::Sale,
+                Some("donation") => DataHeader::Donation,
+                _ => return Err(err("'section' must be \"kind\", \"clth\", \"outfit\", \
+                    \"sale\" or \"donation\".")),
+            };
+            let reasons = match table.remove("reasons") {
+                None => Vec::new(),
+                Some(toml::Value::Array(reasons)) => reasons
+                    .into_iter()
+                    .map(|reason| reason.as_str().map(String::from))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| err("'reasons' must only hold text."))?,
+                Some(_) => return Err(err("'reasons' must be an array.")),
+            };
+            let fields: HashMap<String, Value> = match table.remove("fields") {
+                None => HashMap::new(),
+                Some(toml::Value::Table(fields)) => fields
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from_toml(value)))
+                    .collect(),
+                Some(_) => return Err(err("'fields' must be a table.")),
+            };
+            if let Some(key) = table.keys().next() {
+                return Err(err(&format!("unknown key '{}'.", key)));
+            }
+
+            let chunk = DataChunk { header, line, fields };
+            quarantine.list.push(QuarantinedChunk { chunk, reasons });
+        }
+        Ok(quarantine)
+    }
+}
+
+impl Display for Quarantine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items: Vec<String> = self.list
+            .iter()
+            .enumerate()
+            .map(|(index, item)| format!("{}: {}", index, item))
+            .collect();
+        write!(f, "{}", items.join("\n"))
+    }
+}
+
+/// Validates the quarantined record `index` again against the current
+/// closet, moving it back into `data` if it is valid now. Otherwise its
+/// reasons are updated and returned. Outfits and donations without an id
+/// are given a free one.
+pub fn restore(data: &mut Data, index: usize) -> Result<(), Vec<String>> {
+    let item = data.quarantine.get_mut(index).map_err(|msg| vec![msg])?;
+    let mut chunk = item.chunk.clone();
+    let free_id = match chunk.header {
+        DataHeader::Outfit => Some(data.outfits.request_id()),
+        DataHeader::Donation => Some(data.donations.request_id()),
+        _ => None,
+    };
+    if let Some(id) = free_id {
+        chunk.fields.entry(String::from("id")).or_insert(Value::Num(i64::from(id)));
+    }
+
+    let mut diags = Diagnostics::new();
+    let restored = match chunk.header {
+        DataHeader::Kind => {
+            match chunk.to_kind(&data.kinds, &mut diags) {
+                Some(kind) => {
+                    data.changes.push(Change::Kind(kind.name.clone()));
+                    data.kinds.add(kind).unwrap();
+                    true
+                },
+                None => false,
+            }
+        },
+        DataHeader::Clth => {
+            match chunk.to_clth(&data.kinds, &data.clothes, &mut data.styles, &mut diags) {
+                Some(clth) => {
+                    data.changes.push(Change::AddClth(clth.id));
+                    data.clothes.add(clth);
+                    true
+                },
+                None => false,
+            }
+        },
+        DataHeader::Outfit => {
+            let invalid = data.quarantine.clth_ids();
+            match chunk.to_outfit(&data.clothes, &data.outfits, &invalid, &mut diags) {
+                Some(outfit) => {
+                    data.changes.push(Change::AddOutfit(outfit.id));
+                    data.outfits.add(outfit);
+                    true
+                },
+                None => false,
+            }
+        },
+        DataHeader::Sale => {
+            match chunk.to_sale(&data.clothes, &data.sales, &mut diags) {
+                Some(sale) => {
+                    data.changes.push(Change::Sale(sale.clth));
+                    data.sales.add(sale);
+                    true
+                },
+                None => false,
+            }
+        },
+        DataHeader::Donation => {
+            let invalid = data.quarantine.clth_ids();
+            match chunk.to_donation(&data.clothes, &data.donations, &invalid, &mut diags) {
+                Some(donation) => {
+                    data.changes.push(Change::Donation(donation.id));
+                    data.donations.add(donation);
+                    true
+                },
+                None => false,
+            }
+        },
+    };
+
+    data.changes.push(Change::Quarantine);
+    if restored {
+        data.quarantine.list.remove(index);
+        return Ok(());
+    }
+
+    let item = &mut data.quarantine.list[index];
+    item.reasons = reasons(&diags.list);
+    Err(item.reasons.clone())
+}
+
+/// Deletes the quarantined record `index` for good.
+pub fn discard(data: &mut Data, index: usize) -> Result<(), String> {
+    data.quarantine.remove(index)?;
+    data.changes.push(Change::Quarantine);
+    Ok(())
+}
+
+fn reasons(diags: &[Diagnostic]) -> Vec<String> {
+    diags
+        .iter()
+        .filter(|diag| diag.severity == Severity::Error)
+        .map(Diagnostic::describe)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::read_data;
+
+    const CLOSET: &str = r#"format_version = 3
+
+[[clth]]
+id = 0
+kind = "Chest"
+sex = "Male"
+size = "L"
+color = "FF00EE"
+target = "Keep"
+purchase_date = "2022-08-15"
+style = "summer"
+
+[[clth]]
+id = 1
+kind = "Hat"
+sex = "Male"
+size = "M"
+color = "FF00EE"
+target = "Keep"
+purchase_date = "2022-08-15"
+style = "winter"
+
+[[clth]]
+id = 2
+kind = "Leg"
+sex = "Male"
+size = "M"
+color = "FF00EE"
+target = "Keep"
+purchase_date = "2022-08-15"
+style = "summer"
+
+[[outfit]]
+id = 0
+chest = 0
+leg = 2
+foot = 1
+"#;
+
+    #[test]
+    fn quarantine_invalid_records() {
+        assert!(read_data(CLOSET, false).is_err());
+
+        let (data, diags) = read_data(CLOSET, true).unwrap();
+        assert!(diags.has_errors());
+        assert_eq!(2, data.clothes.list.len());
+        // the rejected style is not created.
+        assert_eq!(vec!["summer"], data.styles.names());
+        assert_eq!(2, data.quarantine.len());
+        assert_eq!(vec![String::from("kind = \"Hat\": Invalid kind.")],
+            data.quarantine.list[0].reasons);
+        assert_eq!(vec![String::from("clothes = [0, 2, 1]: Uses clothing 1, which is invalid.")],
+            data.quarantine.list[1].reasons);
+
+        let text = CLOSET.replacen("id = 1\nkind = \"Hat\"", "id = 3\nkind = \"Hat\"", 1);
+        let (data, _) = read_data(&text, true).unwrap();
+        assert_eq!(vec![String::from("clothes = [0, 2, 1]: No clothing has the id 1.")],
+            data.quarantine.list[1].reasons);
+    }
+
+    #[test]
+    fn quarantine_round_trip() {
+        let (data, _) = read_data(CLOSET, true).unwrap();
+        let text = data.to_toml();
+        assert!(text.contains("[[quarantine]]"));
+
+        // quarantined records don't fail a strict load.
+        let (again, diags) = read_data(&text, false).unwrap();
+        assert!(diags.is_empty());
+        assert_eq!(2, again.quarantine.len());
+        assert_eq!(data.quarantine.list[0].reasons, again.quarantine.list[0].reasons);
+        assert_eq!(text, again.to_toml());
+    }
+
+    #[test]
+    fn repair_and_restore() {
+        let (mut data, _) = read_data(CLOSET, true).unwrap();
+
+        // the outfit still misses its footwear.
+        assert!(restore(&mut data, 1).is_err());
+        assert!(data.quarantine.get_mut(0).unwrap().edit("kind").is_err());
+        data.quarantine.get_mut(0).unwrap().edit("kind = \"Foot\"").unwrap();
+        restore(&mut data, 0).unwrap();
+        // the footwear has a different style than the rest of the outfit.
+        assert_eq!(1, restore(&mut data, 0).unwrap_err().len());
+
+        data.quarantine.remove(0).unwrap();
+        assert!(data.quarantine.is_empty());
+        assert!(data.outfits.list.is_empty());
+        assert_eq!(vec!["summer", "winter"], data.styles.names());
+        assert!(data.quarantine.remove(0).is_err());
+    }
+
+    #[test]
+    fn restore_without_id() {
+        let text = CLOSET.replacen("[[outfit]]\nid = 0\n", "[[outfit]]\n", 1);
+        let (mut data, _) = read_data(&text, true).unwrap();
+        assert!(data.quarantine.list[1].reasons[0].starts_with("id: Missing field."),
+            "{:?}", data.quarantine.list[1].reasons);
+
+        data.quarantine.get_mut(0).unwrap().edit("kind = \"Foot\"").unwrap();
+        data.quarantine.get_mut(0).unwrap().edit("style = \"summer\"").unwrap();
+        restore(&mut data, 0).unwrap();
+        restore(&mut data, 0).unwrap();
+        assert_eq!(vec![0, 2, 1], data.outfits.get(0).unwrap().to_ids());
+        assert!(data.quarantine.is_empty());
+    }
+
+    #[test]
+    fn keep_unsupported_values() {
+        let value = Value::Other(String::from("[1, 2]"));
+        assert_eq!(toml::Value::Array(vec![1.into(), 2.into()]), value.to_toml());
+        assert_eq!(value, Value::from_toml(value.to_toml()));
+    }
+}
@@ -0,0 +1,193 @@
+//! Ledger of the clothes sold, kept as `[[sale]]` entries of the data file.
+//! Selling a clothing archives it as `Status::Sold`.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use chrono::NaiveDate;
+
+use crate::Data;
+use crate::closet::{ ErrMsg, Target };
+use crate::money::{ Amounts, Money };
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sale {
+    /// Id of the clothing sold.
+    pub clth: u32,
+    pub date: NaiveDate,
+    /// Price received.
+    pub price: Money,
+    /// The buyer or where it was sold.
+    pub channel: Option<String>,
+}
+
+impl Sale {
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::value::Table::new();
+        table.insert(String::from("clth"), toml::Value::from(self.clth));
+        table.insert(String::from("date"), toml::Value::from(self.date.to_string()));
+        table.insert(String::from("price"), toml::Value::from(self.price.stored_cents()));
+        table.insert(String::from("currency"), toml::Value::from(self.price.currency.code()));
+        if let Some(channel) = &self.channel {
+            table.insert(String::from("channel"), toml::Value::from(channel.as_str()));
+        }
+        toml::Value::Table(table)
+    }
+}
+
+impl Display for Sale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: clothing {} sold for {}", self.date, self.clth, self.price)?;
+        if let Some(channel) = &self.channel {
+            write!(f, " ({})", channel)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Sales {
+    pub list: Vec<Sale>,
+}
+
+impl Sales {
+    pub fn new() -> Sales {
+        Sales { list: Vec::new() }
+    }
+
+    pub fn add(&mut self, sale: Sale) -> Option<ErrMsg> {
+        if self.get(sale.clth).is_some() {
+            return Some("The clothing has already been sold.");
+        }
+        self.list.push(sale);
+        None
+    }
+
+    /// Returns the sale of the clothing `clth`.
+    pub fn get(&self, clth: u32) -> Option<&Sale> {
+        self.list.iter().find(|sale| sale.clth == clth)
+    }
+
+    pub fn remove(&mut self, clth: u32) -> Result<Sale, ErrMsg> {
+        match self.list.iter().position(|sale| sale.clth == clth) {
+            Some(index) => Ok(self.list.remove(index)),
+            None => Err("Sale not found."),
+        }
+    }
+
+    pub fn to_toml(&self) -> toml::Value {
+        toml::Value::Array(self.list.iter().map(|sale| sale.to_toml()).collect())
+    }
+}
+
+impl Display for Sales {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.list.is_empty() {
+            return write!(f, "No sales to display!");
+        }
+        let lines: Vec<String> = self.list.iter().map(|sale| sale.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Number of clothes sold and what they returned.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Total {
+    pub count: u32,
+    pub received: Amounts,
+}
+
+impl Total {
+    fn add(&mut self, price: &Money) {
+        self.count += 1;
+        self.received.add(price);
+    }
+}
+
+impl Display for Total {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} sold, {}", self.count, self.received)
+    }
+}
+
+/// Sales totals, per month (`YYYY-MM`) and per style.
+pub struct Report {
+    pub by_month: BTreeMap<String, Total>,
+    pub by_style: BTreeMap<String, Total>,
+    pub total: Total,
+    /// What the sold clothes were put on sale for.
+    pub asked: Amounts,
+}
+
+impl Report {
+    pub fn new(data: &Data) -> Report {
+        let mut report = Report {
+            by_month: BTreeMap::new(),
+            by_style: BTreeMap::new(),
+            total: Total::default(),
+            asked: Amounts::new(),
+        };
+
+        for sale in data.sales.list.iter() {
+            let month = sale.date.format("%Y-%m").to_string();
+            report.by_month.entry(month).or_default().add(&sale.price);
+            report.total.add(&sale.price);
+
+            if let Some(clth) = data.clothes.get(sale.clth) {
+                let clth = clth.borrow();
+                report.by_style.entry(clth.style.name.clone()).or_default().add(&sale.price);
+                if let Target::Sale(asked) = &clth.target {
+                    report.asked.add(asked);
+                }
+            }
+        }
+        report
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Sales by month:")?;
+        for (month, total) in self.by_month.iter() {
+            writeln!(f, "  {}: {}", month, total)?;
+        }
+        writeln!(f, "Sales by style:")?;
+        for (style, total) in self.by_style.iter() {
+            writeln!(f, "  {}: {}", style, total)?;
+        }
+        write!(f, "Total: {} (asked {})", self.total, self.asked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::Currency;
+    use crate::storage::read_data;
+    use crate::storage::tests::closet;
+
+    #[test]
+    fn report_sales() {
+        let mut data = closet();
+        let date = |day| NaiveDate::from_ymd(2023, 1, day);
+        let eur = Currency::new("EUR").unwrap();
+        crate::sell_clth(&mut data, Sale { clth: 0, date: date(2), price: Money::usd(1550),
+            channel: Some(String::from("flea market")) }).unwrap();
+        crate::sell_clth(&mut data, Sale { clth: 2, date: date(31),
+            price: Money::new(990, eur.clone()), channel: None }).unwrap();
+        assert!(crate::sell_clth(&mut data, Sale { clth: 2, date: date(31),
+            price: Money::usd(990), channel: None }).is_err());
+        assert!(data.outfits.list.is_empty());
+
+        let report = Report::new(&data);
+        assert_eq!(2, report.total.count);
+        assert_eq!(Money::new(990, eur.clone()), report.total.received.get(&eur));
+        assert_eq!(vec!["2023-01"], report.by_month.keys().collect::<Vec<_>>());
+        assert_eq!(Money::usd(1550), report.by_style["summer"].received.get(&Currency::default()));
+        assert_eq!(Money::usd(2075), report.asked.get(&Currency::default()));
+        assert!(report.to_string().ends_with("Total: 2 sold, €9,90 + $15.50 (asked $20.75)"));
+
+        let again = read_data(&data.to_toml(), false).unwrap().0;
+        assert_eq!(data.sales.list, again.sales.list);
+        assert!(crate::rm_clth(&mut data, 0).is_err());
+    }
+}
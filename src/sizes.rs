@@ -0,0 +1,315 @@
+//! Sizes of clothing.
+//!
+//! A `Size` is a letter (`XXS` to `XXXL`), a number of a regional system
+//! (`42 EU`, `9.5 US`, `10 UK`), a waist and inseam in inches (`32x34`) or a
+//! kids' size by age (`8Y`). Each kind of clothing is measured in a
+//! `SizeSystem`, which tells the sizes that fit it and how its numbers
+//! convert between the EU, US and UK systems. Letter sizes fit every kind,
+//! so a closet keeps its sizes whatever the systems of its kinds.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::closet::{ ErrMsg, Slot };
+
+/// Shoe sizes in tenths, as `[EU, US, UK]`. US sizes are men's sizes.
+const SHOES: [[u16; 3]; 9] = [
+    [390, 65, 55],
+    [400, 75, 65],
+    [410, 80, 70],
+    [420, 90, 80],
+    [430, 100, 90],
+    [440, 105, 95],
+    [450, 115, 105],
+    [460, 120, 110],
+    [470, 130, 120],
+];
+
+/// Numeric clothing sizes (women's dress sizes) in tenths, as `[EU, US, UK]`.
+const CLOTHING: [[u16; 3]; 9] = [
+    [320, 0, 40],
+    [340, 20, 60],
+    [360, 40, 80],
+    [380, 60, 100],
+    [400, 80, 120],
+    [420, 100, 140],
+    [440, 120, 160],
+    [460, 140, 180],
+    [480, 160, 200],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Eu,
+    Us,
+    Uk,
+}
+
+impl Region {
+    pub const ALL: [Region; 3] = [Region::Eu, Region::Us, Region::Uk];
+
+    /// The column of the region in the conversion tables.
+    fn column(self) -> usize {
+        match self {
+            Region::Eu => 0,
+            Region::Us => 1,
+            Region::Uk => 2,
+        }
+    }
+}
+
+impl FromStr for Region {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<Region, ErrMsg> {
+        Region::ALL
+            .into_iter()
+            .find(|region| region.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or("Invalid region.")
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Region::Eu => "EU",
+            Region::Us => "US",
+            Region::Uk => "UK",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Size {
+    XXS,
+    XS,
+    S,
+    M,
+    L,
+    XL,
+    XXL,
+    XXXL,
+    /// A size of a regional system, in tenths so half sizes are exact.
+    Numeric(u16, Region),
+    /// Waist and inseam, in inches.
+    Waist(u8, u8),
+    /// A kids' size, by age in years.
+    Kids(u8),
+}
+
+impl Size {
+    pub const LETTERS: [Size; 8] = [
+        Size::XXS, Size::XS, Size::S, Size::M, Size::L, Size::XL, Size::XXL, Size::XXXL,
+    ];
+}
+
+/// Reads a number with at most one decimal, like `9.5` or `9,5`, in tenths.
+fn parse_tenths(number: &str) -> Result<u16, ErrMsg> {
+    let (units, tenths) = match number.split_once(['.', ',']) {
+        Some((units, tenths)) if tenths.len() == 1 => (units, tenths),
+        Some(_) => return Err("Sizes have at most one decimal."),
+        None => (number, "0"),
+    };
+    let units: u16 = units.parse().map_err(|_| "Invalid size.")?;
+    let tenths: u16 = tenths.parse().map_err(|_| "Invalid size.")?;
+    units.checked_mul(10).and_then(|units| units.checked_add(tenths)).ok_or("Invalid size.")
+}
+
+impl FromStr for Size {
+    type Err = ErrMsg;
+
+    /// Reads any kind of size. Numbers without a region are EU sizes.
+    fn from_str(value: &str) -> Result<Size, ErrMsg> {
+        let value = value.trim().to_lowercase();
+        if let Some(letter) = Size::LETTERS
+            .into_iter()
+            .find(|letter| letter.to_string().eq_ignore_ascii_case(&value))
+        {
+            return Ok(letter);
+        }
+
+        if let Some(age) = value.strip_suffix('y') {
+            return match age.trim().parse() {
+                Ok(age) if age > 0 => Ok(Size::Kids(age)),
+                _ => Err("Invalid size."),
+            };
+        }
+
+        if let Some((waist, inseam)) = value.split_once('x') {
+            return match (waist.trim().parse(), inseam.trim().parse()) {
+                (Ok(waist), Ok(inseam)) if waist > 0 && inseam > 0 => {
+                    Ok(Size::Waist(waist, inseam))
+                },
+                _ => Err("Invalid size."),
+            };
+        }
+
+        let split = value.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(value.len());
+        let (number, region) = value.split_at(split);
+        let region = match region {
+            "" => Region::Eu,
+            region => Region::from_str(region)?,
+        };
+        Ok(Size::Numeric(parse_tenths(number.trim())?, region))
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Size::Numeric(tenths, region) if tenths % 10 == 0 => {
+                write!(f, "{} {}", tenths / 10, region)
+            },
+            Size::Numeric(tenths, region) => {
+                write!(f, "{}.{} {}", tenths / 10, tenths % 10, region)
+            },
+            Size::Waist(waist, inseam) => write!(f, "{}x{}", waist, inseam),
+            Size::Kids(age) => write!(f, "{}Y", age),
+            letter => write!(f, "{:?}", letter),
+        }
+    }
+}
+
+/// How the sizes of a kind of clothing are given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeSystem {
+    /// Letter and kids' sizes.
+    Letter,
+    /// Numeric clothing sizes, like dress sizes.
+    Numeric,
+    /// Shoe sizes.
+    Shoe,
+    /// Waist and inseam, like trousers.
+    Waist,
+}
+
+impl SizeSystem {
+    pub const ALL: [SizeSystem; 4] = [
+        SizeSystem::Letter, SizeSystem::Numeric, SizeSystem::Shoe, SizeSystem::Waist,
+    ];
+
+    /// The system of the kinds worn on `slot`, unless told otherwise.
+    pub fn of_slot(slot: Slot) -> SizeSystem {
+        match slot {
+            Slot::Footwear => SizeSystem::Shoe,
+            Slot::Bottom => SizeSystem::Waist,
+            _ => SizeSystem::Letter,
+        }
+    }
+
+    /// Returns true if `size` can be the size of a clothing of the system.
+    /// Letter sizes fit every system and kids' sizes every one but shoes.
+    pub fn fits(&self, size: &Size) -> bool {
+        match size {
+            Size::Numeric(..) => matches!(self, SizeSystem::Numeric | SizeSystem::Shoe),
+            Size::Waist(..) => *self == SizeSystem::Waist,
+            Size::Kids(_) => *self != SizeSystem::Shoe,
+            _ => true,
+        }
+    }
+
+    /// Returns `size` in the sizes of `region`, if the system's conversion
+    /// table has it.
+    pub fn convert(&self, size: &Size, region: Region) -> Option<Size> {
+        let (tenths, from) = match size {
+            Size::Numeric(tenths, from) => (*tenths, *from),
+            _ => return None,
+        };
+        let table = match self {
+            SizeSystem::Shoe => &SHOES,
+            SizeSystem::Numeric => &CLOTHING,
+            _ => return None,
+        };
+
+        table.iter()
+            .find(|row| row[from.column()] == tenths)
+            .map(|row| Size::Numeric(row[region.column()], region))
+    }
+
+    /// Some sizes of the system, to show how they are written.
+    pub fn examples(&self) -> &'static str {
+        match self {
+            SizeSystem::Letter => "M, XXL or 8Y",
+            SizeSystem::Numeric => "38, 10 UK or M",
+            SizeSystem::Shoe => "42, 9.5 US or M",
+            SizeSystem::Waist => "32x34 or M",
+        }
+    }
+}
+
+impl FromStr for SizeSystem {
+    type Err = ErrMsg;
+
+    fn from_str(value: &str) -> Result<SizeSystem, ErrMsg> {
+        SizeSystem::ALL
+            .into_iter()
+            .find(|system| system.to_string().eq_ignore_ascii_case(value.trim()))
+            .ok_or("Invalid size system.")
+    }
+}
+
+impl fmt::Display for SizeSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SizeSystem::Letter => "letter",
+            SizeSystem::Numeric => "numeric",
+            SizeSystem::Shoe => "shoe",
+            SizeSystem::Waist => "waist",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sizes() {
+        let sizes = [
+            ("xxl", Size::XXL),
+            (" M ", Size::M),
+            ("42", Size::Numeric(420, Region::Eu)),
+            ("9.5 US", Size::Numeric(95, Region::Us)),
+            ("10,5uk", Size::Numeric(105, Region::Uk)),
+            ("32x34", Size::Waist(32, 34)),
+            ("8Y", Size::Kids(8)),
+        ];
+        for (text, size) in sizes {
+            assert_eq!(Ok(size.clone()), Size::from_str(text), "{}", text);
+            assert_eq!(Ok(size.clone()), Size::from_str(&size.to_string()));
+        }
+        assert_eq!("9.5 US", Size::Numeric(95, Region::Us).to_string());
+
+        for text in ["", "XXXXL", "9.25", "42 FR", "32x", "0Y", "x34", "-1"] {
+            assert!(Size::from_str(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn fit_sizes_to_systems() {
+        let waist = Size::Waist(32, 34);
+        assert!(SizeSystem::Waist.fits(&waist) && !SizeSystem::Letter.fits(&waist));
+        assert!(SizeSystem::Shoe.fits(&Size::Numeric(420, Region::Eu)));
+        assert!(!SizeSystem::Shoe.fits(&Size::Kids(8)));
+        assert!(SizeSystem::ALL.iter().all(|system| system.fits(&Size::L)));
+        assert_eq!(SizeSystem::Shoe, SizeSystem::of_slot(Slot::Footwear));
+    }
+
+    #[test]
+    fn convert_sizes() {
+        let shoe = Size::Numeric(420, Region::Eu);
+        assert_eq!(Some(Size::Numeric(90, Region::Us)),
+            SizeSystem::Shoe.convert(&shoe, Region::Us));
+        assert_eq!(Some(Size::Numeric(80, Region::Uk)),
+            SizeSystem::Shoe.convert(&shoe, Region::Uk));
+        assert_eq!(Some(shoe.clone()), SizeSystem::Shoe.convert(&shoe, Region::Eu));
+
+        let dress = Size::Numeric(100, Region::Uk);
+        assert_eq!(Some(Size::Numeric(380, Region::Eu)),
+            SizeSystem::Numeric.convert(&dress, Region::Eu));
+        assert_eq!(None, SizeSystem::Shoe.convert(&Size::Numeric(255, Region::Eu), Region::Us));
+        assert_eq!(None, SizeSystem::Waist.convert(&shoe, Region::Us));
+    }
+}
@@ -0,0 +1,602 @@
+//! SQLite store of the closet.
+//!
+//! Kinds, clothes, styles, outfits and sales are kept in tables of the same name as the
+//! data file sections, with the outfits referencing their clothes through the
+//! `outfit_clth` table. Rows are read back as data chunks, so they go through the
+//! same validation (and lenient quarantine) as the data file, and the format
+//! version is kept in `PRAGMA user_version`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use rusqlite::{ Connection, Transaction, TransactionBehavior, params, params_from_iter };
+use rusqlite::types::{ Value as SqlValue, ValueRef };
+
+use crate::Data;
+use crate::quarantine::Quarantine;
+use crate::store::{ Change, Store };
+use crate::storage::{ DataChunk, DataHeader, Diagnostics, FileData, Loaded, Source, Value };
+use crate::storage::{ self, FORMAT_VERSION };
+
+const SCHEMA: &str = "
+    CREATE TABLE kind (
+        name TEXT PRIMARY KEY,
+        slot TEXT NOT NULL,
+        sizes TEXT
+    );
+    CREATE TABLE style (
+        name TEXT PRIMARY KEY
+    );
+    CREATE TABLE clth (
+        id INTEGER PRIMARY KEY,
+        kind TEXT NOT NULL,
+        sex TEXT NOT NULL,
+        size TEXT NOT NULL,
+        color TEXT NOT NULL,
+        target TEXT NOT NULL,
+        purchase_date TEXT NOT NULL,
+        style TEXT NOT NULL REFERENCES style (name),
+        status TEXT NOT NULL,
+        purchase_price INTEGER,
+        currency TEXT,
+        wears INTEGER
+    );
+    CREATE TABLE outfit (
+        id INTEGER PRIMARY KEY
+    );
+    CREATE TABLE outfit_clth (
+        outfit INTEGER NOT NULL REFERENCES outfit (id) ON DELETE CASCADE,
+        clth INTEGER NOT NULL REFERENCES clth (id),
+        PRIMARY KEY (outfit, clth)
+    );
+    CREATE TABLE sale (
+        clth INTEGER PRIMARY KEY REFERENCES clth (id),
+        date TEXT NOT NULL,
+        price INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        channel TEXT
+    );
+    CREATE TABLE donation (
+        id INTEGER PRIMARY KEY,
+        recipient TEXT NOT NULL,
+        date TEXT NOT NULL
+    );
+    CREATE TABLE donated (
+        clth INTEGER PRIMARY KEY REFERENCES clth (id),
+        donation INTEGER NOT NULL REFERENCES donation (id) ON DELETE CASCADE
+    );
+    CREATE TABLE quarantine (
+        id INTEGER PRIMARY KEY,
+        entry TEXT NOT NULL
+    );
+    CREATE TABLE draft (
+        section TEXT PRIMARY KEY,
+        entry TEXT NOT NULL
+    );";
+
+const DROP: &str = "
+    DROP TABLE IF EXISTS donated;
+    DROP TABLE IF EXISTS donation;
+    DROP TABLE IF EXISTS sale;
+    DROP TABLE IF EXISTS outfit_clth;
+    DROP TABLE IF EXISTS outfit;
+    DROP TABLE IF EXISTS clth;
+    DROP TABLE IF EXISTS style;
+    DROP TABLE IF EXISTS kind;
+    DROP TABLE IF EXISTS quarantine;
+    DROP TABLE IF EXISTS draft;";
+
+fn db_err(err: rusqlite::Error) -> String {
+    format!("Database error: {}", err)
+}
+
+fn to_sql(value: &toml::Value) -> SqlValue {
+    match value {
+        toml::Value::String(text) => SqlValue::Text(text.clone()),
+        toml::Value::Integer(num) => SqlValue::Integer(*num),
+        toml::Value::Float(num) => SqlValue::Real(*num),
+        toml::Value::Boolean(flag) => SqlValue::Integer(*flag as i64),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn from_sql(value: ValueRef) -> Option<Value> {
+    match value {
+        ValueRef::Null => None,
+        ValueRef::Integer(num) => Some(Value::from_toml(toml::Value::Integer(num))),
+        ValueRef::Real(num) => Some(Value::Other(num.to_string())),
+        ValueRef::Text(text) => Some(Value::Text(String::from_utf8_lossy(text).into_owned())),
+        ValueRef::Blob(_) => Some(Value::Other(String::from("<blob>"))),
+    }
+}
+
+/// Inserts the fields of `row` (a TOML table) into `table`, replacing the
+/// values of the row with the same `key` column in place. Columns missing
+/// from `row` (its unset optional fields) are cleared.
+fn write_row(tx: &Transaction, table: &str, key: &str, row: &toml::Value)
+    -> Result<(), String>
+{
+    let row = row.as_table().expect("Rows are written from TOML tables.");
+    let columns: Vec<&str> = row.keys().map(String::as_str).collect();
+    let params: Vec<String> = (1..=columns.len()).map(|n| format!("?{}", n)).collect();
+    let stmt = tx.prepare(&format!("SELECT * FROM {} LIMIT 0", table)).map_err(db_err)?;
+    let cleared = stmt.column_names()
+        .into_iter()
+        .filter(|column| !row.contains_key(*column))
+        .map(|column| format!("{} = NULL", column));
+    let updates: Vec<String> = columns
+        .iter()
+        .map(|column| format!("{0} = excluded.{0}", column))
+        .chain(cleared)
+        .collect();
+
+    let sql = format!("INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {}",
+        table, columns.join(", "), params.join(", "), key, updates.join(", "));
+    tx.execute(&sql, params_from_iter(row.values().map(to_sql))).map_err(db_err)?;
+    Ok(())
+}
+
+fn write_kind(tx: &Transaction, data: &Data, name: &str) -> Result<(), String> {
+    match data.kinds.custom().iter().find(|kind| kind.name == name) {
+        Some(kind) => write_row(tx, "kind", "name", &kind.to_toml()),
+        None => {
+            tx.execute("DELETE FROM kind WHERE name = ?1", params![name]).map_err(db_err)?;
+            Ok(())
+        },
+    }
+}
+
+fn write_style(tx: &Transaction, name: &str) -> Result<(), String> {
+    tx.execute("INSERT OR IGNORE INTO style (name) VALUES (?1)", params![name])
+        .map_err(db_err)?;
+    Ok(())
+}
+
+fn write_clth(tx: &Transaction, data: &Data, id: u32) -> Result<(), String> {
+    // a clothing removed later in the same batch has nothing to write.
+    if let Some(clth) = data.clothes.get(id) {
+        let clth = clth.borrow();
+        write_style(tx, &clth.style.name)?;
+        write_row(tx, "clth", "id", &clth.to_toml())?;
+    }
+    Ok(())
+}
+
+/// Writes an outfit, its clothes going to the `outfit_clth` table.
+fn write_outfit(tx: &Transaction, data: &Data, id: u32) -> Result<(), String> {
+    // the outfit_clth rows go along, by the foreign keys.
+    tx.execute("DELETE FROM outfit WHERE id = ?1", params![id]).map_err(db_err)?;
+    let outfit = match data.outfits.get(id) {
+        Some(outfit) => outfit,
+        None => return Ok(()),
+    };
+
+    let mut row = outfit.to_toml();
+    row.as_table_mut().unwrap().remove("clothes");
+    write_row(tx, "outfit", "id", &row)?;
+    for clth in outfit.to_ids() {
+        tx.execute("INSERT INTO outfit_clth (outfit, clth) VALUES (?1, ?2)", params![id, clth])
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+fn write_sale(tx: &Transaction, data: &Data, clth: u32) -> Result<(), String> {
+    match data.sales.get(clth) {
+        Some(sale) => write_row(tx, "sale", "clth", &sale.to_toml()),
+        None => {
+            tx.execute("DELETE FROM sale WHERE clth = ?1", params![clth]).map_err(db_err)?;
+            Ok(())
+        },
+    }
+}
+
+/// Writes a donation, its clothes going to the `donated` table.
+fn write_donation(tx: &Transaction, data: &Data, id: u32) -> Result<(), String> {
+    // the donated rows go along, by the foreign keys.
+    tx.execute("DELETE FROM donation WHERE id = ?1", params![id]).map_err(db_err)?;
+    let donation = match data.donations.get(id) {
+        Some(donation) => donation,
+        None => return Ok(()),
+    };
+
+    let mut row = donation.to_toml();
+    row.as_table_mut().unwrap().remove("clothes");
+    write_row(tx, "donation", "id", &row)?;
+    for clth in donation.clothes.iter() {
+        tx.execute("INSERT INTO donated (clth, donation) VALUES (?1, ?2)", params![clth, id])
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+fn write_quarantine(tx: &Transaction, quarantine: &Quarantine) -> Result<(), String> {
+    tx.execute("DELETE FROM quarantine", []).map_err(db_err)?;
+    for item in quarantine.list.iter() {
+        let entry = toml::to_string(&item.to_toml())
+            .expect("Quarantined records are always representable as TOML.");
+        tx.execute("INSERT INTO quarantine (entry) VALUES (?1)", params![entry])
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+/// Writes the drafts of `data`, each one as the TOML of its `draft` table.
+fn write_drafts(tx: &Transaction, data: &Data) -> Result<(), String> {
+    tx.execute("DELETE FROM draft", []).map_err(db_err)?;
+    let drafts = match data.cache.to_toml() {
+        Some(toml::Value::Table(drafts)) => drafts,
+        _ => return Ok(()),
+    };
+
+    for (section, fields) in drafts {
+        let entry = toml::to_string(&fields)
+            .expect("Drafts are always representable as TOML.");
+        tx.execute("INSERT INTO draft (section, entry) VALUES (?1, ?2)",
+            params![section, entry]).map_err(db_err)?;
+    }
+    Ok(())
+}
+
+fn data_version(conn: &Connection) -> Result<i64, String> {
+    conn.pragma_query_value(None, "data_version", |row| row.get(0)).map_err(db_err)
+}
+
+pub struct SqliteStore {
+    conn: Connection,
+    /// `PRAGMA data_version` as last loaded, which changes whenever another
+    /// connection commits to the database.
+    data_version: i64,
+}
+
+impl SqliteStore {
+    pub fn open(path: &Path) -> Result<SqliteStore, String> {
+        let conn = Connection::open(path)
+            .map_err(|err| format!("Can't open {}: {}", path.display(), err))?;
+        SqliteStore::from(conn)
+    }
+
+    pub fn in_memory() -> Result<SqliteStore, String> {
+        SqliteStore::from(Connection::open_in_memory().map_err(db_err)?)
+    }
+
+    fn from(conn: Connection) -> Result<SqliteStore, String> {
+        conn.pragma_update(None, "foreign_keys", true).map_err(db_err)?;
+        let data_version = data_version(&conn)?;
+        Ok(SqliteStore { conn, data_version })
+    }
+
+    /// Starts a write transaction, refusing it if another program changed
+    /// the database since it was loaded.
+    fn transaction(&mut self) -> Result<Transaction<'_>, String> {
+        let loaded = self.data_version;
+        let tx = self.conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)
+            .map_err(db_err)?;
+        if data_version(&tx)? != loaded {
+            return Err(String::from("The database was changed by another program since \
+                it was loaded. Restart recloset to load those changes; yours were not \
+                saved."));
+        }
+        Ok(tx)
+    }
+
+    /// Format version of the database, 0 if it is empty.
+    pub fn version(&self) -> Result<u32, String> {
+        self.conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(db_err)
+    }
+
+    fn chunks(&self, header: DataHeader) -> Result<Vec<DataChunk>, String> {
+        let sql = format!("SELECT * FROM {} ORDER BY rowid", header.name());
+        let mut stmt = self.conn.prepare(&sql).map_err(db_err)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+        let mut chunks = Vec::new();
+        let mut rows = stmt.query([]).map_err(db_err)?;
+        while let Some(row) = rows.next().map_err(db_err)? {
+            let mut fields = HashMap::new();
+            for (index, column) in columns.iter().enumerate() {
+                if let Some(value) = from_sql(row.get_ref(index).map_err(db_err)?) {
+                    fields.insert(column.clone(), value);
+                }
+            }
+            chunks.push(DataChunk { header, line: 0, fields });
+        }
+        Ok(chunks)
+    }
+
+    /// Reads the rows of `header`, with the ids of their clothes, kept in
+    /// `table` by their `owner` column, as a `clothes` list.
+    fn with_clothes(&self, header: DataHeader, table: &str, owner: &str)
+        -> Result<Vec<DataChunk>, String>
+    {
+        let mut chunks = self.chunks(header)?;
+        let sql = format!("SELECT clth FROM {} WHERE {} = ?1 ORDER BY rowid", table, owner);
+        let mut stmt = self.conn.prepare(&sql).map_err(db_err)?;
+
+        for chunk in chunks.iter_mut() {
+            let id = match chunk.fields.get("id") {
+                Some(Value::Num(id)) => *id,
+                _ => continue,
+            };
+            let ids = stmt
+                .query_map(params![id], |row| row.get::<_, i64>(0))
+                .map_err(db_err)?
+                .map(|id| id.map(|id| id.to_string()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(db_err)?;
+            chunk.fields.insert(String::from("clothes"),
+                Value::Other(format!("[{}]", ids.join(", "))));
+        }
+        Ok(chunks)
+    }
+
+    fn quarantine(&self) -> Result<Quarantine, String> {
+        let mut stmt = self.conn
+            .prepare("SELECT entry FROM quarantine ORDER BY id")
+            .map_err(db_err)?;
+        let entries = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+
+        let items = entries
+            .iter()
+            .map(|entry| entry.parse::<toml::Value>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| format!("Invalid quarantine entry: {}", err))?;
+        Quarantine::from_toml(toml::Value::Array(items), "").map_err(|err| err.msg)
+    }
+
+    fn drafts(&self) -> Result<Vec<DataChunk>, String> {
+        let mut stmt = self.conn.prepare("SELECT section, entry FROM draft").map_err(db_err)?;
+        let entries = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+
+        let mut drafts = toml::value::Table::new();
+        for (section, entry) in entries {
+            let fields = entry
+                .parse::<toml::Value>()
+                .map_err(|err| format!("Invalid draft: {}", err))?;
+            drafts.insert(section, fields);
+        }
+        storage::from_drafts(toml::Value::Table(drafts), "").map_err(|err| err.msg)
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&mut self, lenient: bool) -> Result<Loaded, String> {
+        self.data_version = data_version(&self.conn)?;
+        let version = self.version()?;
+        if version == 0 {
+            let diagnostics = Diagnostics::new();
+            return Ok(Loaded { data: Data::new(), source: Source::New, diagnostics });
+        }
+
+        let mut fdata = FileData {
+            version,
+            // user-defined kinds are kept since version 10.
+            kind_chunks: if version >= 10 { self.chunks(DataHeader::Kind)? } else { Vec::new() },
+            clth_chunks: self.chunks(DataHeader::Clth)?,
+            // outfits keep their clothes apart since version 11.
+            outfit_chunks: if version >= 11 {
+                self.with_clothes(DataHeader::Outfit, "outfit_clth", "outfit")?
+            } else {
+                self.chunks(DataHeader::Outfit)?
+            },
+            // sales are kept since version 6.
+            sale_chunks: if version >= 6 { self.chunks(DataHeader::Sale)? } else { Vec::new() },
+            // and donations since version 7.
+            donation_chunks: if version >= 7 {
+                self.with_clothes(DataHeader::Donation, "donated", "donation")?
+            } else {
+                Vec::new()
+            },
+            quarantine: self.quarantine()?,
+            // drafts are kept since version 4.
+            drafts: if version >= 4 { self.drafts()? } else { Vec::new() },
+        };
+        fdata.migrate()?;
+
+        let (mut data, diagnostics) = fdata.read(lenient)?;
+        // rows can only be updated in place once the schema is current.
+        if version < FORMAT_VERSION {
+            data.changes.push(Change::All);
+        }
+        Ok(Loaded { data, source: Source::Main, diagnostics })
+    }
+
+    fn save(&mut self, data: &Data) -> Result<(), String> {
+        let tx = self.transaction()?;
+        tx.execute_batch(DROP).map_err(db_err)?;
+        tx.execute_batch(SCHEMA).map_err(db_err)?;
+        tx.pragma_update(None, "user_version", FORMAT_VERSION).map_err(db_err)?;
+
+        for kind in data.kinds.custom() {
+            write_row(&tx, "kind", "name", &kind.to_toml())?;
+        }
+        for name in data.styles.names() {
+            write_style(&tx, name)?;
+        }
+        for clth in data.clothes.list.iter() {
+            write_row(&tx, "clth", "id", &clth.borrow().to_toml())?;
+        }
+        for outfit in data.outfits.list.iter() {
+            write_outfit(&tx, data, outfit.id)?;
+        }
+        for sale in data.sales.list.iter() {
+            write_row(&tx, "sale", "clth", &sale.to_toml())?;
+        }
+        for donation in data.donations.list.iter() {
+            write_donation(&tx, data, donation.id)?;
+        }
+        write_quarantine(&tx, &data.quarantine)?;
+        write_drafts(&tx, data)?;
+        tx.commit().map_err(db_err)
+    }
+
+    fn update(&mut self, data: &Data, changes: &[Change]) -> Result<(), String> {
+        if changes.contains(&Change::All) || self.version()? != FORMAT_VERSION {
+            return self.save(data);
+        }
+
+        let tx = self.transaction()?;
+        for change in changes {
+            match change {
+                Change::Kind(name) => write_kind(&tx, data, name)?,
+                Change::AddClth(id) | Change::UpdateClth(id) => write_clth(&tx, data, *id)?,
+                Change::RemoveClth(id) => {
+                    // the outfits using it go first, so nothing references it.
+                    tx.execute("DELETE FROM outfit WHERE id IN \
+                        (SELECT outfit FROM outfit_clth WHERE clth = ?1)", params![id])
+                        .map_err(db_err)?;
+                    tx.execute("DELETE FROM clth WHERE id = ?1", params![id])
+                        .map_err(db_err)?;
+                },
+                Change::AddOutfit(id) => write_outfit(&tx, data, *id)?,
+                Change::RemoveOutfit(id) => {
+                    tx.execute("DELETE FROM outfit WHERE id = ?1", params![id])
+                        .map_err(db_err)?;
+                },
+                Change::Sale(clth) => write_sale(&tx, data, *clth)?,
+                Change::Donation(id) => write_donation(&tx, data, *id)?,
+                Change::Quarantine => write_quarantine(&tx, &data.quarantine)?,
+                Change::Draft => write_drafts(&tx, data)?,
+                Change::All => unreachable!("A full update saves the whole closet."),
+            }
+        }
+        tx.commit().map_err(db_err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::closet;
+
+    fn count(store: &SqliteStore, table: &str) -> u32 {
+        let sql = format!("SELECT COUNT(*) FROM {}", table);
+        store.conn.query_row(&sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        assert!(matches!(store.load(false).unwrap().source, Source::New));
+
+        let data = closet();
+        store.save(&data).unwrap();
+        assert_eq!(FORMAT_VERSION, store.version().unwrap());
+
+        let loaded = store.load(false).unwrap();
+        assert!(loaded.diagnostics.is_empty());
+        assert_eq!(data.to_toml(), loaded.data.to_toml());
+    }
+
+    #[test]
+    fn update_rows() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        let mut data = closet();
+        store.save(&data).unwrap();
+
+        data.clothes.get(2).unwrap().borrow_mut().color = crate::closet::Rgb(0, 0, 255);
+        crate::rm_clth(&mut data, 1).unwrap();
+        assert_eq!(vec![Change::RemoveClth(1)], data.changes);
+        data.changes.push(Change::UpdateClth(2));
+        store.commit(&mut data).unwrap();
+        assert!(data.changes.is_empty());
+
+        // removing the footwear removes the outfit using it as well.
+        assert_eq!(2, count(&store, "clth"));
+        assert_eq!(0, count(&store, "outfit"));
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+
+        let date = chrono::NaiveDate::from_ymd(2023, 1, 2);
+        let price = crate::money::Money::usd(990);
+        let sale = crate::sales::Sale { clth: 2, date, price, channel: None };
+        crate::sell_clth(&mut data, sale).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!(1, count(&store, "sale"));
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+
+        crate::history::undo(&mut data).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!(0, count(&store, "sale"));
+
+        data.clothes.get(0).unwrap().borrow_mut().target = crate::closet::Target::Donation;
+        crate::donate(&mut data, "Red Cross", date, &[0]).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!((1, 1), (count(&store, "donation"), count(&store, "donated")));
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+
+        // clearing an optional field clears its column.
+        let cost = crate::money::Money::usd(1500);
+        crate::set_cost(&mut data, 2, Some(cost)).unwrap();
+        store.commit(&mut data).unwrap();
+        crate::set_cost(&mut data, 2, None).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+
+        let kind = crate::closet::Kind::new("Scarf", crate::closet::Slot::Accessory);
+        crate::add_kind(&mut data, kind).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!(1, count(&store, "kind"));
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+        crate::history::undo(&mut data).unwrap();
+        store.commit(&mut data).unwrap();
+        assert_eq!(0, count(&store, "kind"));
+    }
+
+    #[test]
+    fn refuse_external_changes() {
+        let dir = crate::storage::tests::tmp_dir("sqlite-external");
+        let path = dir.join("closet.db");
+        let mut store = SqliteStore::open(&path).unwrap();
+        let mut data = closet();
+        store.save(&data).unwrap();
+        data.changes.push(Change::UpdateClth(0));
+        store.commit(&mut data).unwrap();
+
+        let other = SqliteStore::open(&path).unwrap();
+        other.conn.execute("DELETE FROM outfit", []).unwrap();
+        data.changes.push(Change::UpdateClth(0));
+        assert!(store.commit(&mut data).is_err());
+        assert!(store.save(&data).is_err());
+        assert_eq!(0, count(&store, "outfit"));
+
+        store.load(false).unwrap();
+        store.commit(&mut data).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn enforce_foreign_keys() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        store.save(&closet()).unwrap();
+
+        let tx = store.conn.transaction().unwrap();
+        let sql = "INSERT INTO outfit_clth (outfit, clth) VALUES (0, 9)";
+        assert!(tx.execute(sql, []).is_err());
+    }
+
+    #[test]
+    fn quarantine_invalid_rows() {
+        let mut store = SqliteStore::in_memory().unwrap();
+        store.save(&closet()).unwrap();
+        store.conn.execute("UPDATE clth SET kind = 'Hat' WHERE id = 0", []).unwrap();
+
+        assert!(store.load(false).is_err());
+        let loaded = store.load(true).unwrap();
+        assert_eq!(2, loaded.data.quarantine.len());
+
+        store.save(&loaded.data).unwrap();
+        let again = store.load(false).unwrap().data;
+        assert_eq!(2, again.quarantine.len());
+        assert_eq!(2, again.clothes.list.len());
+    }
+}
@@ -1,11 +1,25 @@
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{ Hash, Hasher };
+use std::ffi::OsString;
 use std::fmt::Display;
+use std::fs::{ self, File };
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
 use std::rc::Rc;
+use std::str::FromStr;
 use chrono::NaiveDate;
+use toml_edit::{ DocumentMut, Item, Table };
 
-use crate::Data;
-use crate::closet::{ Kind, Sex, Size, Rgb, Target};
+use crate::{ Data, TmpCache };
+use crate::quarantine::Quarantine;
+use crate::closet::{ Kind, Kinds, Sex, Slot, Rgb, Status, Target };
 use crate::closet::{ Clth, Clothes, Outfits, Outfit, Styles };
+use crate::closet::{ ClthBuffer, OutfitBuffer };
+use crate::sales::{ Sale, Sales };
+use crate::donations::{ Donation, Donations };
+use crate::money::{ Currency, Money };
+use crate::sizes::{ Size, SizeSystem };
 
 #[derive(Debug)]
 pub struct ParseError {
@@ -25,199 +39,1282 @@ impl Display for ParseError {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found on a chunk of the data file.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Line of the chunk header, 0 if unknown.
+    pub line: u32,
+    pub field: Option<String>,
+    /// The offending value, as written in the file.
+    pub value: Option<String>,
+    pub msg: String,
+}
+
+impl Diagnostic {
+    /// Describes the problem without its severity and line.
+    pub fn describe(&self) -> String {
+        match (&self.field, &self.value) {
+            (Some(field), Some(value)) => format!("{} = {}: {}", field, value, self.msg),
+            (Some(field), None) => format!("{}: {}", field, self.msg),
+            _ => self.msg.clone(),
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        };
+        write!(f, "{}", severity)?;
+        if self.line != 0 {
+            write!(f, " on line {}", self.line)?;
+        }
+        match (&self.field, &self.value) {
+            (Some(field), Some(value)) => write!(f, " ({} = {})", field, value)?,
+            (Some(field), None) => write!(f, " ({})", field)?,
+            _ => (),
+        }
+        write!(f, ": {}", &self.msg)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Diagnostics {
+    pub list: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Diagnostics {
+        Diagnostics { list: Vec::new() }
+    }
+
+    pub fn push(&mut self, severity: Severity, line: u32, field: Option<&str>,
+        value: Option<&Value>, msg: &str)
+    {
+        self.list.push(Diagnostic {
+            severity,
+            line,
+            field: field.map(String::from),
+            value: value.map(|value| value.to_string()),
+            msg: String::from(msg),
+        });
+    }
+
+    pub fn error(&mut self, line: u32, field: Option<&str>, value: Option<&Value>,
+        msg: &str)
+    {
+        self.push(Severity::Error, line, field, value, msg);
+    }
+
+    pub fn warning(&mut self, line: u32, field: Option<&str>, value: Option<&Value>,
+        msg: &str)
+    {
+        self.push(Severity::Warning, line, field, value, msg);
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.list.iter().any(|diag| diag.severity == Severity::Error)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+}
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines: Vec<String> = self.list.iter().map(|diag| diag.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+pub const DATA_ENV: &str = "RECLOSET_DATA";
+pub const DATA_FILE: &str = "data.toml";
+
+/// Resolves the data file location. `flag` (the `--data` option) takes
+/// precedence over `$RECLOSET_DATA`, which takes precedence over the XDG
+/// config directory (`$XDG_CONFIG_HOME`, or `$HOME/.config` when unset).
+pub fn data_path<F>(flag: Option<&str>, get_env: F) -> Result<PathBuf, String>
+    where F: Fn(&str) -> Option<OsString>
+{
+    if let Some(path) = flag {
+        return Ok(PathBuf::from(path));
+    }
+
+    let var = |key: &str| get_env(key).filter(|value| !value.is_empty());
+
+    if let Some(path) = var(DATA_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+
+    let config_dir = match (var("XDG_CONFIG_HOME"), var("HOME")) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(home)) => PathBuf::from(home).join(".config"),
+        (None, None) => return Err(format!(
+            "Can't locate the data file: use '--data' or set ${}.", DATA_ENV)),
+    };
+    Ok(config_dir.join("recloset").join(DATA_FILE))
+}
+
+/// Appends `suffix` to the file name of `path` (`data.toml` -> `data.toml.bak`).
+pub fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+pub fn backup_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".bak")
+}
+
+/// Hashes the content of the file at `path`, `None` if there is no file.
+/// Used to find out if the file was changed by someone else.
+pub fn fingerprint(path: &Path) -> io::Result<Option<u64>> {
+    match fs::read(path) {
+        Ok(bytes) => {
+            let mut hasher = DefaultHasher::new();
+            bytes.hash(&mut hasher);
+            Ok(Some(hasher.finish()))
+        },
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes `text` to `path` without ever leaving a half written file behind.
+///
+/// The content goes to a temporary file in the same directory, which is
+/// fsynced and then renamed over `path`. The version being replaced is kept
+/// as the backup file (see `backup_path`).
+pub fn save(path: &Path, text: &str) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    let tmp_path = sibling_path(path, ".tmp");
+    let mut tmp = File::create(&tmp_path)?;
+    tmp.write_all(text.as_bytes())?;
+    tmp.sync_all()?;
+    drop(tmp);
+
+    if path.exists() {
+        let backup = backup_path(path);
+        if backup.exists() {
+            fs::remove_file(&backup)?;
+        }
+        // a hard link keeps the old content without copying it, but not
+        // every file system supports them.
+        if fs::hard_link(path, &backup).is_err() {
+            fs::copy(path, &backup)?;
+        }
+    }
+
+    fs::rename(&tmp_path, path)?;
+
+    // persist the rename itself; not every platform can open directories.
+    if let Ok(dir) = File::open(dir) {
+        dir.sync_all().ok();
+    }
+    Ok(())
+}
+
+/// Parses the content of a data file into `Data`, along with the problems
+/// found on it. Every error found is reported at once. When `lenient` is
+/// set, data errors don't fail: the invalid records are quarantined instead.
+pub fn read_data(text: &str, lenient: bool) -> Result<(Data, Diagnostics), String> {
+    FileData::from(text).map_err(|err| err.to_string())?.read(lenient)
+}
+
+/// Field identifying each record of the arrays of tables, to match the
+/// records of two versions of the file.
+const RECORD_IDS: [(&str, &str); 5] = [
+    ("kind", "name"), ("clth", "id"), ("outfit", "id"), ("sale", "clth"), ("donation", "id"),
+];
+
+/// Carries the comments and spacing of the file `old` over to `new`, its
+/// content as regenerated by `Data::to_toml`. Records are matched by their
+/// id and fields by their name; what can't be matched, like the comments of
+/// a removed record, is dropped. `new` is returned as is when `old` isn't a
+/// TOML document, e.g. a legacy file.
+pub fn keep_comments(old: &str, new: &str) -> String {
+    let (old, mut doc) = match (old.parse::<DocumentMut>(), new.parse::<DocumentMut>()) {
+        (Ok(old), Ok(doc)) => (old, doc),
+        _ => return String::from(new),
+    };
+    copy_decor(old.as_table(), doc.as_table_mut());
+    doc.set_trailing(old.trailing().clone());
+
+    for (name, id) in RECORD_IDS {
+        let (Some(records), Some(new_records)) = (
+            old.get(name).and_then(Item::as_array_of_tables),
+            doc.get_mut(name).and_then(Item::as_array_of_tables_mut),
+        ) else {
+            continue;
+        };
+        for table in new_records.iter_mut() {
+            // ids are integers, but the names of kinds are strings.
+            let key = |record: &Table| record.get(id).and_then(|item| {
+                item.as_integer().map(|id| id.to_string()).or(item.as_str().map(String::from))
+            });
+            let same = |record: &&Table| key(record) == key(table);
+            if let Some(record) = records.iter().find(same) {
+                *table.decor_mut() = record.decor().clone();
+                copy_decor(record, table);
+            }
+        }
+    }
+    doc.to_string()
+}
+
+/// Copies the decor of the keys and values of `from` to the same keys of `to`.
+fn copy_decor(from: &Table, to: &mut Table) {
+    for (key, item) in from.iter() {
+        if let Some(mut new_key) = to.key_mut(key) {
+            *new_key.leaf_decor_mut() = from.key(key).unwrap().leaf_decor().clone();
+        }
+        if let (Some(value), Some(Item::Value(new_value))) = (item.as_value(), to.get_mut(key)) {
+            *new_value.decor_mut() = value.decor().clone();
+        }
+    }
+}
+
+pub enum Source {
+    /// The data file was read successfully.
+    Main,
+    /// The data file does not exist yet.
+    New,
+    /// The data file was damaged and the backup was loaded instead. Holds
+    /// the error found on the data file, which is left as it is until
+    /// `restore_backup` is called.
+    Backup { err: String },
+}
+
+pub struct Loaded {
+    pub data: Data,
+    pub source: Source,
+    /// Warnings, plus the errors of the quarantined records on a lenient load.
+    pub diagnostics: Diagnostics,
+}
+
+/// Loads the data file at `path`, falling back to its backup when the file
+/// can't be parsed. Nothing is written: see `restore_backup` to replace the
+/// damaged file. Invalid records fail the load. See `read_data` for
+/// `lenient`.
+pub fn load(path: &Path, lenient: bool) -> Result<Loaded, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            let diagnostics = Diagnostics::new();
+            return Ok(Loaded { data: Data::new(), source: Source::New, diagnostics });
+        },
+        Err(err) => return Err(err.to_string()),
+    };
+
+    // only a file that can't be parsed is replaced: errors in its records
+    // are reported, leaving the file for the user to fix.
+    let err = match FileData::from(&text) {
+        Ok(fdata) => {
+            let (data, diagnostics) = fdata.read(lenient)?;
+            return Ok(Loaded { data, source: Source::Main, diagnostics });
+        },
+        Err(err) => err.to_string(),
+    };
+
+    let backup = backup_path(path);
+    let (data, diagnostics) = match fs::read_to_string(&backup) {
+        Ok(text) => match read_data(&text, lenient) {
+            Ok(loaded) => loaded,
+            Err(_) => return Err(err),
+        },
+        Err(_) => return Err(err),
+    };
+
+    Ok(Loaded { data, source: Source::Backup { err }, diagnostics })
+}
+
+/// Moves the damaged data file at `path` out of the way and copies its
+/// backup back in its place, returning where the damaged file went.
+pub fn restore_backup(path: &Path) -> Result<PathBuf, String> {
+    let moved_to = sibling_path(path, ".damaged");
+    fs::rename(path, &moved_to)
+        .and_then(|_| fs::copy(backup_path(path), path))
+        .map_err(|err| format!("Can't restore the backup: {}", err))?;
+    Ok(moved_to)
+}
+
+/// Version of the layout written by `Data::to_toml`. Files without a
+/// `format_version` header are version 1.
+pub const FORMAT_VERSION: u32 = 12;
+
+/// Upgrades a file to the version it is paired with in `MIGRATIONS`.
+pub type Migration = fn(&mut FileData) -> Result<(), String>;
+
+/// The versions older files have to be migrated to, in order. The others
+/// only add optional sections or fields, which older files simply lack:
+/// the header (2), drafts (4), sales (6), donations (7), purchase prices
+/// (8), currencies (9), kinds (10) and size systems (12).
+pub const MIGRATIONS: [(u32, Migration); 3] = [
+    (3, v2_to_v3),
+    (5, v4_to_v5),
+    (11, v10_to_v11),
+];
+
+/// Version 3 stores the outfit ids. Older files get the ids they were
+/// loaded with, which follow the order of the outfits in the file.
+fn v2_to_v3(fdata: &mut FileData) -> Result<(), String> {
+    let quarantined = fdata.quarantine.list
+        .iter_mut()
+        .filter(|item| matches!(item.chunk.header, DataHeader::Outfit))
+        .map(|item| &mut item.chunk);
+
+    for (id, chunk) in fdata.outfit_chunks.iter_mut().chain(quarantined).enumerate() {
+        if chunk.fields.contains_key("id") {
+            return Err(String::from("Outfit ids are not supported before version 3."));
+        }
+        let id = i64::try_from(id).map_err(|_| String::from("Too many outfits."))?;
+        chunk.fields.insert(String::from("id"), Value::Num(id));
+    }
+    Ok(())
+}
+
+/// Version 5 stores the lifecycle status of the clothes. Every clothing of
+/// an older file is still in use.
+fn v4_to_v5(fdata: &mut FileData) -> Result<(), String> {
+    let quarantined = fdata.quarantine.list
+        .iter_mut()
+        .filter(|item| matches!(item.chunk.header, DataHeader::Clth))
+        .map(|item| &mut item.chunk);
+
+    for chunk in fdata.clth_chunks.iter_mut().chain(quarantined) {
+        if chunk.fields.contains_key("status") {
+            return Err(String::from("Clothing status is not supported before version 5."));
+        }
+        chunk.fields.insert(String::from("status"), Value::Text(String::from("Active")));
+    }
+    Ok(())
+}
+
+/// Version 11 lets outfits hold any set of clothes: their `chest`, `leg`
+/// and `foot` fields become a `clothes` list, drafts included.
+fn v10_to_v11(fdata: &mut FileData) -> Result<(), String> {
+    let quarantined = fdata.quarantine.list
+        .iter_mut()
+        .filter(|item| matches!(item.chunk.header, DataHeader::Outfit))
+        .map(|item| &mut item.chunk);
+    let drafts = fdata.drafts
+        .iter_mut()
+        .filter(|chunk| matches!(chunk.header, DataHeader::Outfit));
+
+    for chunk in fdata.outfit_chunks.iter_mut().chain(quarantined).chain(drafts) {
+        if chunk.fields.contains_key("clothes") {
+            return Err(String::from("Outfit clothes lists are not supported before \
+                version 11."));
+        }
+        let ids: Vec<String> = ["chest", "leg", "foot"]
+            .iter()
+            .filter_map(|key| chunk.fields.remove(*key))
+            .map(|value| value.to_string())
+            .collect();
+        chunk.fields.insert(String::from("clothes"), Value::Other(format!("[{}]", ids.join(", "))));
+    }
+    Ok(())
+}
+
 pub struct FileData {
+    pub version: u32,
+    pub kind_chunks: Vec<DataChunk>,
     pub clth_chunks: Vec<DataChunk>,
     pub outfit_chunks: Vec<DataChunk>,
+    pub sale_chunks: Vec<DataChunk>,
+    pub donation_chunks: Vec<DataChunk>,
+    /// Records quarantined by an earlier lenient load.
+    pub quarantine: Quarantine,
+    /// The unfinished clothing and outfit, at most one of each.
+    pub drafts: Vec<DataChunk>,
+}
+
+impl Default for FileData {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl FileData {
     pub fn new() -> FileData {
         FileData {
+            version: FORMAT_VERSION,
+            kind_chunks: Vec::new(),
             clth_chunks: Vec::new(),
-            outfit_chunks: Vec::new()
+            outfit_chunks: Vec::new(),
+            sale_chunks: Vec::new(),
+            donation_chunks: Vec::new(),
+            quarantine: Quarantine::new(),
+            drafts: Vec::new(),
         }
     }
 
+    /// Parses a data file. TOML documents are read by the TOML backend,
+    /// while files in the legacy layout (blank-line separated `[clth]` and
+    /// `[outfit]` chunks, which is not valid TOML once a header repeats) go
+    /// through `into_chunks`. Older format versions are migrated up to
+    /// `FORMAT_VERSION`.
     pub fn from(text: &str) -> Result<FileData, ParseError> {
-        let mut fdata = FileData::new();
-        let chunks = match into_chunks(text) {
-            Ok(value) => value,
-            Err(err) => return Err(err),
+        let chunks = match text.parse::<toml::Value>() {
+            Ok(doc) => return FileData::from_value(doc, text),
+            Err(_) if is_legacy(text) => into_chunks(text)?,
+            Err(err) => {
+                let line = err.line_col().map_or(0, |(line, _)| line as u32 + 1);
+                let msg = err.to_string();
+                // the toml error message carries its own position suffix.
+                let msg = match msg.split_once(" at line ") {
+                    Some((msg, _)) => String::from(msg),
+                    None => msg,
+                };
+                return Err(ParseError::new(line, msg));
+            },
+        };
+
+        FileData { version: 1, ..FileData::new() }.with_chunks(chunks)
+    }
+
+    /// Reads an already parsed document. `text` is the source the document
+    /// was parsed from, used to locate the chunks; it may be empty.
+    pub fn from_value(mut doc: toml::Value, text: &str) -> Result<FileData, ParseError> {
+        let version = take_version(&mut doc)?;
+        let mut section = |name: &str| doc.as_table_mut().and_then(|root| root.remove(name));
+        let quarantine = match section("quarantine") {
+            Some(value) => Quarantine::from_toml(value, text)?,
+            None => Quarantine::new(),
         };
+        let drafts = match section("draft") {
+            Some(value) => from_drafts(value, text)?,
+            None => Vec::new(),
+        };
+
+        let fdata = FileData { version, quarantine, drafts, ..FileData::new() };
+        fdata.with_chunks(from_document(doc, text)?)
+    }
 
+    /// Sorts `chunks` into the file sections, then migrates the file.
+    fn with_chunks(mut self, chunks: Vec<DataChunk>) -> Result<FileData, ParseError> {
         for chunk in chunks {
             match chunk.header {
-                DataHeader::Clth => fdata.clth_chunks.push(chunk),
-                DataHeader::Outfit => fdata.outfit_chunks.push(chunk),
+                DataHeader::Kind => self.kind_chunks.push(chunk),
+                DataHeader::Clth => self.clth_chunks.push(chunk),
+                DataHeader::Outfit => self.outfit_chunks.push(chunk),
+                DataHeader::Sale => self.sale_chunks.push(chunk),
+                DataHeader::Donation => self.donation_chunks.push(chunk),
+            }
+        }
+
+        self.migrate().map_err(|msg| ParseError::new(0, msg))?;
+        Ok(self)
+    }
+
+    /// Applies the migrations needed to bring the file to `FORMAT_VERSION`,
+    /// in order.
+    pub fn migrate(&mut self) -> Result<(), String> {
+        if self.version == 0 || self.version > FORMAT_VERSION {
+            return Err(format!("Unsupported format version {} (this build \
+                reads up to version {}).", self.version, FORMAT_VERSION));
+        }
+
+        for (version, step) in MIGRATIONS {
+            if self.version < version {
+                step(self).map_err(|msg| {
+                    format!("Migration to version {} failed: {}", version, msg)
+                })?;
+                self.version = version;
+            }
+        }
+        self.version = FORMAT_VERSION;
+        Ok(())
+    }
+
+    /// Builds the built-in kinds along with the user-defined ones of every
+    /// valid `[kind]` chunk. The problems of the others are reported to
+    /// `diags` and the chunks themselves are put in `quarantine`.
+    pub fn extract_kinds(&self, diags: &mut Diagnostics, quarantine: &mut Quarantine)
+        -> Kinds
+    {
+        let mut kinds = Kinds::new();
+
+        for chunk in &self.kind_chunks {
+            let first = diags.list.len();
+            match chunk.to_kind(&kinds, diags) {
+                Some(kind) => { kinds.add(kind).unwrap(); },
+                None => quarantine.add(chunk.clone(), &diags.list[first..]),
             }
         }
-        Ok(fdata)
+        kinds
     }
 
-    pub fn extract_clths(&self) -> Result<(Clothes, Styles), &'static str> {
+    /// Builds the clothes (and their styles) of every valid `[clth]` chunk,
+    /// which must be of a kind of `kinds`. The problems of the others are
+    /// reported to `diags` and the chunks themselves are put in `quarantine`.
+    pub fn extract_clths(&self, kinds: &Kinds, diags: &mut Diagnostics,
+        quarantine: &mut Quarantine) -> (Clothes, Styles)
+    {
         let mut clothes = Clothes::new();
         let mut styles = Styles::new();
 
         for chunk in &self.clth_chunks {
-            let id = match chunk.fields.get("id") {
-                Some(Value::Num(num)) => *num as u32,
-                Some(_) => return Err("'id' is not a text field."),
-                None => return Err("Missing 'id' field."),
-            };
+            let first = diags.list.len();
+            match chunk.to_clth(kinds, &clothes, &mut styles, diags) {
+                Some(clth) => { clothes.add(clth); },
+                None => quarantine.add(chunk.clone(), &diags.list[first..]),
+            }
+        }
+        (clothes, styles)
+    }
 
-            let kind = match chunk.fields.get("kind") {
-                Some(Value::Text(value)) => Kind::from_str(value),
-                Some(_) => return Err("'kind' is not a numerical field."),
-                None => return Err("Missing 'kind' field."),
-            };
+    /// Builds the outfits of every valid `[outfit]` chunk. The problems of
+    /// the others are reported to `diags` and the chunks themselves are put
+    /// in `quarantine`.
+    pub fn extract_outfits(&self, clothes: &Clothes, diags: &mut Diagnostics,
+        quarantine: &mut Quarantine) -> Outfits
+    {
+        let mut outfits = Outfits::new();
+        let invalid = quarantine.clth_ids();
 
-            let kind = match kind {
-                Ok(value) => value,
-                Err(msg) => return Err(msg),
-            };
+        for chunk in &self.outfit_chunks {
+            let first = diags.list.len();
+            match chunk.to_outfit(clothes, &outfits, &invalid, diags) {
+                Some(outfit) => { outfits.add(outfit); },
+                None => quarantine.add(chunk.clone(), &diags.list[first..]),
+            }
+        }
+        outfits
+    }
 
-            let sex = match chunk.fields.get("sex") {
-                Some(Value::Text(value)) => Sex::from_str(value),
-                Some(_) => return Err("'sex' is not a numerical field."),
-                None => return Err("Missing 'sex' field."),
-            };
+    /// Builds the sales of every valid `[sale]` chunk. The problems of the
+    /// others are reported to `diags` and the chunks themselves are put in
+    /// `quarantine`.
+    pub fn extract_sales(&self, clothes: &Clothes, diags: &mut Diagnostics,
+        quarantine: &mut Quarantine) -> Sales
+    {
+        let mut sales = Sales::new();
 
-            let sex = match sex {
-                Ok(value) => value,
-                Err(msg) => return Err(msg),
-            };
+        for chunk in &self.sale_chunks {
+            let first = diags.list.len();
+            match chunk.to_sale(clothes, &sales, diags) {
+                Some(sale) => { sales.add(sale); },
+                None => quarantine.add(chunk.clone(), &diags.list[first..]),
+            }
+        }
+        sales
+    }
 
-            let size = match chunk.fields.get("size") {
-                Some(Value::Text(value)) => Size::from_str(value),
-                Some(_) => return Err("'size' is not a numerical field."),
-                None => return Err("Missing 'size' field."),
-            };
+    /// Builds the donations of every valid `[donation]` chunk. The problems
+    /// of the others are reported to `diags` and the chunks themselves are
+    /// put in `quarantine`.
+    pub fn extract_donations(&self, clothes: &Clothes, diags: &mut Diagnostics,
+        quarantine: &mut Quarantine) -> Donations
+    {
+        let mut donations = Donations::new();
+        let invalid = quarantine.clth_ids();
 
-            let size = match size {
-                Ok(value) => value,
-                Err(msg) => return Err(msg),
-            };
+        for chunk in &self.donation_chunks {
+            let first = diags.list.len();
+            match chunk.to_donation(clothes, &donations, &invalid, diags) {
+                Some(donation) => { donations.add(donation); },
+                None => quarantine.add(chunk.clone(), &diags.list[first..]),
+            }
+        }
+        donations
+    }
 
-            let color = match chunk.fields.get("color") {
-                Some(Value::Text(value)) => Rgb::try_from_hex(value),
-                Some(_) => return Err("'color' is not a numerical field."),
-                None => return Err("Missing 'color' field."),
-            };
+    /// Builds the drafts left on the last session. Drafts are never an
+    /// error: their invalid fields are reported as warnings and dropped.
+    pub fn extract_drafts(&self, kinds: &Kinds, clothes: &Clothes, diags: &mut Diagnostics)
+        -> TmpCache
+    {
+        let mut cache = TmpCache::new();
+        let mut found = Diagnostics::new();
+        for chunk in &self.drafts {
+            match chunk.header {
+                DataHeader::Clth => cache.clth = Some(chunk.to_clth_buffer(kinds, &mut found)),
+                DataHeader::Outfit => {
+                    cache.outfit = Some(chunk.to_outfit_buffer(clothes, &mut found));
+                },
+                DataHeader::Kind | DataHeader::Sale | DataHeader::Donation => {
+                    unreachable!("Only clothes and outfits have drafts.")
+                },
+            }
+        }
 
-            let color = match color {
-                Some(color) => color,
-                None => return Err("Invalid color."),
-            };
+        for mut diag in found.list {
+            diag.severity = Severity::Warning;
+            diag.msg = format!("{} The draft field was dropped.", diag.msg);
+            diags.list.push(diag);
+        }
+        cache
+    }
 
-            let target = match chunk.fields.get("target") {
-                Some(Value::Text(value)) => Target::from_str(value),
-                Some(_) => return Err("'target' is not a numerical field."),
-                None => return Err("Missing 'target' field."),
-            };
+    /// Builds the valid part of the file, along with the problems found on
+    /// the rest of it. The invalid chunks end up in the data quarantine.
+    pub fn load(&self) -> (Data, Diagnostics) {
+        let mut diags = Diagnostics::new();
+        let mut quarantine = self.quarantine.clone();
+        let kinds = self.extract_kinds(&mut diags, &mut quarantine);
+        let (clothes, styles) = self.extract_clths(&kinds, &mut diags, &mut quarantine);
+        let outfits = self.extract_outfits(&clothes, &mut diags, &mut quarantine);
+        let sales = self.extract_sales(&clothes, &mut diags, &mut quarantine);
+        let donations = self.extract_donations(&clothes, &mut diags, &mut quarantine);
+        let cache = self.extract_drafts(&kinds, &clothes, &mut diags);
 
-            let target = match target {
-                Ok(value) => value,
-                Err(msg) => return Err(msg) ,
-            };
+        let data = Data { kinds, clothes, styles, outfits, sales, donations, quarantine, cache,
+            ..Data::new() };
+        (data, diags)
+    }
 
-            let purchase_date = match chunk.fields.get("purchase_date") {
-                Some(Value::Text(value)) => {
-                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
-                },
-                Some(_) => return Err("'purchase_date' is not a numerical field."),
-                None => return Err("Missing 'purchase_date' field."),
-            };
+    /// Like `to_data`, but quarantines the invalid records instead of failing
+    /// when `lenient` is set. Errors are formatted one per line.
+    pub fn read(&self, lenient: bool) -> Result<(Data, Diagnostics), String> {
+        match self.load() {
+            (_, diags) if diags.has_errors() && !lenient => {
+                Err(diags.to_string().replace('\n', "\n  "))
+            },
+            loaded => Ok(loaded),
+        }
+    }
 
-            let purchase_date = match purchase_date {
-                Ok(date) => date,
-                Err(_) => return Err("Invalid date."),
-            };
+    /// Builds the data of the file, failing with every problem found if any
+    /// of them is an error.
+    pub fn to_data(&self) -> Result<Data, Diagnostics> {
+        match self.load() {
+            (_, diags) if diags.has_errors() => Err(diags),
+            (data, _) => Ok(data),
+        }
+    }
+}
 
-            let stl_name = match chunk.fields.get("style") {
-                Some(Value::Text(name)) => name,
-                Some(_) => return Err("'style' is not a numerical field."),
-                None => return Err("Missing 'style' field."),
-            };
-            let style = styles.get_or_add(&stl_name);
+/// Why a record can't use the clothing `id`, which is not in the closet:
+/// either its record was rejected, if it is in `invalid`, or there is none.
+fn missing_clth(id: u32, invalid: &[u32]) -> String {
+    match invalid.contains(&id) {
+        true => format!("Uses clothing {}, which is invalid.", id),
+        false => format!("No clothing has the id {}.", id),
+    }
+}
 
-            clothes.add(
-                Clth::new(id, kind, sex, size, color, target, purchase_date, style)
-            );
+const KIND_FIELDS: [&str; 3] = ["name", "slot", "sizes"];
+
+const CLTH_FIELDS: [&str; 12] = [
+    "id", "kind", "sex", "size", "color", "target", "purchase_date", "style", "status",
+    "purchase_price", "currency", "wears"
+];
+
+const OUTFIT_FIELDS: [&str; 2] = ["id", "clothes"];
+
+const SALE_FIELDS: [&str; 5] = ["clth", "date", "price", "currency", "channel"];
+
+const DONATION_FIELDS: [&str; 4] = ["id", "recipient", "date", "clothes"];
+
+const CLTH_DRAFT_FIELDS: [&str; 6] = ["kind", "sex", "size", "color", "price", "target"];
+
+const OUTFIT_DRAFT_FIELDS: [&str; 1] = ["clothes"];
+
+#[derive(Clone, Copy)]
+pub enum DataHeader {
+    Kind,
+    Clth,
+    Outfit,
+    Sale,
+    Donation,
+}
+
+impl DataHeader {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataHeader::Kind => "kind",
+            DataHeader::Clth => "clth",
+            DataHeader::Outfit => "outfit",
+            DataHeader::Sale => "sale",
+            DataHeader::Donation => "donation",
         }
-        Ok((clothes, styles))
     }
+}
 
-    pub fn extract_outfits(&self, clothes: &Clothes) -> Result<Outfits, &'static str> {
-        let mut outfits = Outfits::new();
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Num(i64),
+    /// Any value of an unsupported type, as written in the file.
+    Other(String),
+}
 
-        for chunk in &self.outfit_chunks {
-            let chest_id = match chunk.fields.get("chest") {
-                Some(Value::Num(num)) => *num as u32,
-                Some(_) => return Err("'chest' is not a text field."),
-                None => return Err("Missing 'chest' field."),
-            };
-            let chest = match clothes.get(chest_id) {
-                Some(rc) => Rc::downgrade(rc),
-                None => return Err("Invalid 'chest' id."),
-            };
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Text(text) => write!(f, "{:?}", text),
+            Value::Num(num) => write!(f, "{}", num),
+            Value::Other(value) => write!(f, "{}", value),
+        }
+    }
+}
 
-            let leg_id = match chunk.fields.get("leg") {
-                Some(Value::Num(num)) => *num as u32,
-                Some(_) => return Err("'leg' is not a text field."),
-                None => return Err("Missing 'leg' field."),
-            };
-            let leg = match clothes.get(leg_id) {
-                Some(rc) => Rc::downgrade(rc),
-                None => return Err("Invalid 'leg' id."),
-            };
+impl Value {
+    pub fn from_toml(value: toml::Value) -> Value {
+        match value {
+            toml::Value::String(text) => Value::Text(text),
+            toml::Value::Integer(num) => Value::Num(num),
+            other => Value::Other(other.to_string()),
+        }
+    }
 
-            let foot_id = match chunk.fields.get("foot") {
-                Some(Value::Num(num)) => *num as u32,
-                Some(_) => return Err("'foot' is not a text field."),
-                None => return Err("Missing 'foot' field."),
-            };
-            let foot = match clothes.get(foot_id) {
-                Some(rc) => Rc::downgrade(rc),
-                None => return Err("Invalid 'foot' id."),
-            };
+    pub fn to_toml(&self) -> toml::Value {
+        match self {
+            Value::Text(text) => toml::Value::from(text.as_str()),
+            Value::Num(num) => toml::Value::from(*num),
+            // written back as it was read, or as text if it can't be parsed.
+            Value::Other(value) => format!("v = {}", value)
+                .parse::<toml::Value>()
+                .ok()
+                .and_then(|mut doc| doc.as_table_mut()?.remove("v"))
+                .unwrap_or_else(|| toml::Value::from(value.as_str())),
+        }
+    }
+}
 
-            let outfit = match Outfit::new(outfits.request_id(), chest, leg, foot) {
-                Ok(value) => value,
-                Err(msg) => return Err(msg),
+#[derive(Clone)]
+pub struct DataChunk{
+    pub header: DataHeader,
+    pub line: u32,
+    pub fields: HashMap<String, Value>,
+}
+
+impl DataChunk {
+    /// Reports fields that aren't in `known` as warnings.
+    fn check_fields(&self, known: &[&str], diags: &mut Diagnostics) {
+        let mut unknown: Vec<_> = self.fields
+            .iter()
+            .filter(|(key, _)| !known.contains(&key.as_str()))
+            .collect();
+        unknown.sort_by_key(|(key, _)| *key);
+
+        for (key, value) in unknown {
+            diags.warning(self.line, Some(key), Some(value), "Unknown field.");
+        }
+    }
+
+    fn text(&self, key: &str, diags: &mut Diagnostics) -> Option<&str> {
+        match self.fields.get(key) {
+            Some(Value::Text(value)) => Some(value),
+            Some(value) => {
+                diags.error(self.line, Some(key), Some(value), "Expected a text value.");
+                None
+            },
+            None => {
+                diags.error(self.line, Some(key), None, "Missing field.");
+                None
+            },
+        }
+    }
+
+    /// Reads a clothing id (a non negative integer).
+    fn id(&self, key: &str, diags: &mut Diagnostics) -> Option<u32> {
+        match self.fields.get(key) {
+            Some(Value::Num(num)) if u32::try_from(*num).is_ok() => Some(*num as u32),
+            Some(value) => {
+                diags.error(self.line, Some(key), Some(value), "Expected a non negative \
+                    (32-bit) integer.");
+                None
+            },
+            None => {
+                diags.error(self.line, Some(key), None, "Missing field.");
+                None
+            },
+        }
+    }
+
+    /// Builds the kind described by the chunk, reporting its problems to
+    /// `diags`. Its name must not be taken in `kinds`.
+    pub fn to_kind(&self, kinds: &Kinds, diags: &mut Diagnostics) -> Option<Kind> {
+        self.check_fields(&KIND_FIELDS, diags);
+
+        let name = self.text("name", diags).map(str::trim);
+        let slot = self.parse("slot", diags, Slot::from_str);
+        let sizes = match self.fields.get("sizes") {
+            None => slot.map(|slot| Some(SizeSystem::of_slot(slot))),
+            Some(_) => self.parse("sizes", diags, SizeSystem::from_str).map(Some),
+        };
+
+        let msg = match name {
+            Some("") => Some("A kind needs a name."),
+            Some(name) if kinds.get(name).is_some() => {
+                Some("A kind with the same name already exists.")
+            },
+            _ => None,
+        };
+        if let Some(msg) = msg {
+            diags.error(self.line, Some("name"), self.fields.get("name"), msg);
+            return None;
+        }
+        let mut kind = Kind::new(name?, slot?);
+        kind.sizes = sizes??;
+        Some(kind)
+    }
+
+    /// Builds the clothing described by the chunk, reporting its problems to
+    /// `diags`. Its kind must be one of `kinds` and its id must not be taken
+    /// in `clothes`, while its style is taken from (or added to) `styles`.
+    pub fn to_clth(&self, kinds: &Kinds, clothes: &Clothes, styles: &mut Styles,
+        diags: &mut Diagnostics) -> Option<Clth>
+    {
+        self.check_fields(&CLTH_FIELDS, diags);
+
+        let id = self.id("id", diags);
+        let kind = self.parse("kind", diags, |value| kinds.parse(value));
+        let sex = self.parse("sex", diags, Sex::from_str);
+        let size = self.size(kind.as_deref(), diags);
+        let color = self.parse("color", diags, |value| {
+            Rgb::try_from_hex(value).ok_or("Invalid color.")
+        });
+        let target = self.parse("target", diags, Target::from_str);
+        let purchase_date = self.parse("purchase_date", diags, |value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| "Invalid date.")
+        });
+        let stl_name = self.text("style", diags);
+        let status = self.parse("status", diags, Status::from_str);
+        let cost = self.cost(diags);
+        let wears = match self.fields.get("wears") {
+            None => Some(0),
+            Some(_) => self.id("wears", diags),
+        };
+
+        if let Some(id) = id {
+            if clothes.get(id).is_some() {
+                diags.error(self.line, Some("id"), self.fields.get("id"),
+                    "A clothing with the same id already exists.");
+                return None;
+            }
+        }
+
+        // the style is only added once the whole clothing is known to be valid.
+        let (id, kind, sex, size, color, target, purchase_date, stl_name, status) =
+            (id?, kind?, sex?, size?, color?, target?, purchase_date?, stl_name?, status?);
+        let (cost, wears) = (cost?, wears?);
+        let style = styles.get_or_add(stl_name);
+        let mut clth = Clth::new(id, kind, sex, size, color, target, purchase_date, style);
+        clth.status = status;
+        clth.cost = cost;
+        clth.wears = wears;
+        Some(clth)
+    }
+
+    /// Reads the size of a clothing, which must fit its `kind` when known.
+    fn size(&self, kind: Option<&Kind>, diags: &mut Diagnostics) -> Option<Size> {
+        let size = self.parse("size", diags, Size::from_str)?;
+        match kind {
+            Some(kind) if !kind.sizes.fits(&size) => {
+                diags.error(self.line, Some("size"), self.fields.get("size"),
+                    "The size doesn't fit the kind.");
+                None
+            },
+            _ => Some(size),
+        }
+    }
+
+    /// Reads the optional purchase price of a clothing.
+    fn cost(&self, diags: &mut Diagnostics) -> Option<Option<Money>> {
+        match self.fields.get("purchase_price") {
+            None if self.fields.contains_key("currency") => {
+                diags.error(self.line, Some("currency"), self.fields.get("currency"),
+                    "A currency needs a purchase price.");
+                None
+            },
+            None => Some(None),
+            Some(_) => self.money("purchase_price", diags).map(Some),
+        }
+    }
+
+    /// Reads a non negative amount of cents at `key`, which the storage
+    /// formats hold as signed 64-bit integers.
+    fn cents(&self, key: &str, diags: &mut Diagnostics) -> Option<u64> {
+        match self.fields.get(key) {
+            Some(Value::Num(num)) if *num >= 0 => Some(*num as u64),
+            Some(value) => {
+                diags.error(self.line, Some(key), Some(value),
+                    "Expected a non negative amount of cents.");
+                None
+            },
+            None => {
+                diags.error(self.line, Some(key), None, "Missing field.");
+                None
+            },
+        }
+    }
+
+    /// Reads an amount of cents at `key`, in the currency of the `currency`
+    /// field (`DEFAULT_CURRENCY` when missing).
+    fn money(&self, key: &str, diags: &mut Diagnostics) -> Option<Money> {
+        let cents = self.cents(key, diags);
+        let currency = match self.fields.get("currency") {
+            None => Some(Currency::default()),
+            Some(_) => self.parse("currency", diags, Currency::new),
+        };
+        Some(Money::new(cents?, currency?))
+    }
+
+    /// Builds the outfit described by the chunk, reporting its problems to
+    /// `diags`. It must only reference clothes of `clothes`, and neither its
+    /// id nor its clothes may be taken in `outfits`. `invalid` are the ids of
+    /// the clothes whose records were rejected.
+    pub fn to_outfit(&self, clothes: &Clothes, outfits: &Outfits, invalid: &[u32],
+        diags: &mut Diagnostics) -> Option<Outfit>
+    {
+        self.check_fields(&OUTFIT_FIELDS, diags);
+
+        let id = self.id("id", diags);
+        if let Some(id) = id {
+            if outfits.get(id).is_some() {
+                diags.error(self.line, Some("id"), self.fields.get("id"),
+                    "An outfit with the same id already exists.");
+                return None;
+            }
+        }
+
+        let ids = self.ids("clothes", diags);
+        let value = self.fields.get("clothes");
+        let mut clths = Vec::new();
+        for clth_id in ids.iter().flatten() {
+            match clothes.get(*clth_id) {
+                Some(rc) if !rc.borrow().status.is_active() => {
+                    let msg = format!("Clothing {} is archived.", clth_id);
+                    diags.error(self.line, Some("clothes"), value, &msg);
+                    return None;
+                },
+                Some(rc) => clths.push(Rc::downgrade(rc)),
+                None => {
+                    let msg = missing_clth(*clth_id, invalid);
+                    diags.error(self.line, Some("clothes"), value, &msg);
+                    return None;
+                },
+            }
+        }
+
+        let result = Outfit::new(id?, ids.map(|_| clths)?).and_then(|outfit| {
+            match outfits.find(&outfit.to_ids()) {
+                Some(_) => Err("This outfit already exists!"),
+                None => Ok(outfit),
+            }
+        });
+
+        match result {
+            Ok(outfit) => Some(outfit),
+            Err(msg) => {
+                diags.error(self.line, None, None, msg);
+                None
+            },
+        }
+    }
+
+    /// Builds the sale described by the chunk, reporting its problems to
+    /// `diags`. It must reference a sold clothing of `clothes` that has no
+    /// sale in `sales` yet.
+    pub fn to_sale(&self, clothes: &Clothes, sales: &Sales, diags: &mut Diagnostics)
+        -> Option<Sale>
+    {
+        self.check_fields(&SALE_FIELDS, diags);
+
+        let clth = self.id("clth", diags);
+        let date = self.parse("date", diags, |value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| "Invalid date.")
+        });
+        let price = self.money("price", diags);
+        let channel = match self.fields.get("channel") {
+            None => Some(None),
+            Some(_) => self.text("channel", diags).map(|text| Some(String::from(text))),
+        };
+
+        if let Some(id) = clth {
+            let msg = match clothes.get(id) {
+                None => Some("No clothing has this id."),
+                Some(rc) if !matches!(rc.borrow().status, Status::Sold(_)) => {
+                    Some("The clothing is not sold.")
+                },
+                Some(_) if sales.get(id).is_some() => Some("The clothing has already been sold."),
+                Some(_) => None,
             };
-            outfits.add(outfit);
+            if let Some(msg) = msg {
+                diags.error(self.line, Some("clth"), self.fields.get("clth"), msg);
+                return None;
+            }
         }
-        Ok(outfits)
+
+        Some(Sale { clth: clth?, date: date?, price: price?, channel: channel? })
     }
 
-    pub fn to_data(&self) -> Result<Data, &'static str> {
-        let (clothes, styles) = match self.extract_clths() {
-            Ok(values) => values,
-            Err(msg) => return Err(msg),
+    /// Reads a list of clothing ids.
+    fn ids(&self, key: &str, diags: &mut Diagnostics) -> Option<Vec<u32>> {
+        let value = match self.fields.get(key) {
+            Some(value) => value,
+            None => {
+                diags.error(self.line, Some(key), None, "Missing field.");
+                return None;
+            },
         };
 
-        let outfits = match self.extract_outfits(&clothes) {
-            Ok(value) => value,
-            Err(msg) => return Err(msg),
+        let ids = match value.to_toml() {
+            toml::Value::Array(items) => items
+                .iter()
+                .map(|item| item.as_integer().and_then(|num| u32::try_from(num).ok()))
+                .collect::<Option<Vec<u32>>>(),
+            _ => None,
+        };
+        if ids.is_none() {
+            diags.error(self.line, Some(key), Some(value), "Expected a list of clothing ids.");
+        }
+        ids
+    }
+
+    /// Builds the donation described by the chunk, reporting its problems to
+    /// `diags`. It must only hold donated clothes of `clothes` that are in
+    /// no other donation of `donations`. `invalid` are as for `to_outfit`.
+    pub fn to_donation(&self, clothes: &Clothes, donations: &Donations, invalid: &[u32],
+        diags: &mut Diagnostics) -> Option<Donation>
+    {
+        self.check_fields(&DONATION_FIELDS, diags);
+
+        let id = self.id("id", diags);
+        let recipient = self.text("recipient", diags);
+        let date = self.parse("date", diags, |value| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| "Invalid date.")
+        });
+        let ids = self.ids("clothes", diags);
+
+        if let Some(id) = id {
+            if donations.get(id).is_some() {
+                diags.error(self.line, Some("id"), self.fields.get("id"),
+                    "A donation with the same id already exists.");
+                return None;
+            }
+        }
+
+        let value = self.fields.get("clothes");
+        for clth_id in ids.iter().flatten() {
+            let msg = match clothes.get(*clth_id) {
+                None => Some(missing_clth(*clth_id, invalid)),
+                Some(rc) if !matches!(rc.borrow().status, Status::Donated(_)) => {
+                    Some(format!("Clothing {} is not donated.", clth_id))
+                },
+                Some(_) if donations.of(*clth_id).is_some() => {
+                    Some(format!("Clothing {} is in another donation.", clth_id))
+                },
+                Some(_) => None,
+            };
+            if let Some(msg) = msg {
+                diags.error(self.line, Some("clothes"), value, &msg);
+                return None;
+            }
+        }
+        if ids.as_ref().is_some_and(|ids| ids.is_empty()) {
+            diags.error(self.line, Some("clothes"), value, "A donation must hold some clothing.");
+            return None;
+        }
+
+        Some(Donation {
+            id: id?,
+            recipient: String::from(recipient?),
+            date: date?,
+            clothes: ids?,
+        })
+    }
+
+    /// Builds the clothing draft described by the chunk. Missing fields are
+    /// left to be filled, while invalid ones are reported to `diags`.
+    pub fn to_clth_buffer(&self, kinds: &Kinds, diags: &mut Diagnostics) -> ClthBuffer {
+        self.check_fields(&CLTH_DRAFT_FIELDS, diags);
+
+        let filled = |key: &str| self.fields.contains_key(key);
+        let mut buffer = ClthBuffer::new();
+        if filled("kind") { buffer.kind = self.parse("kind", diags, |value| kinds.parse(value)); }
+        if filled("sex") { buffer.sex = self.parse("sex", diags, Sex::from_str); }
+        if filled("size") { buffer.size = self.size(buffer.kind.as_deref(), diags); }
+        if filled("color") {
+            buffer.color = self.parse("color", diags, |value| {
+                Rgb::try_from_hex(value).ok_or("Invalid color.")
+            });
+        }
+        buffer.price = match self.fields.get("price") {
+            // drafts of older versions hold the price in cents of dollars.
+            Some(Value::Num(_)) => self.cents("price", diags).map(Money::usd),
+            Some(_) => self.parse("price", diags, Money::from_str),
+            None => None,
         };
+        if filled("target") { buffer.target = self.parse("target", diags, Target::from_str); }
+        buffer
+    }
+
+    /// Builds the outfit draft described by the chunk, with the clothes of
+    /// `clothes` selected so far.
+    pub fn to_outfit_buffer(&self, clothes: &Clothes, diags: &mut Diagnostics)
+        -> OutfitBuffer
+    {
+        self.check_fields(&OUTFIT_DRAFT_FIELDS, diags);
+
+        let mut buffer = OutfitBuffer::new();
+        if !self.fields.contains_key("clothes") {
+            return buffer;
+        }
+        let value = self.fields.get("clothes");
+        for clth_id in self.ids("clothes", diags).into_iter().flatten() {
+            match clothes.get(clth_id) {
+                Some(rc) if !rc.borrow().status.is_active() => {
+                    let msg = format!("Clothing {} is archived.", clth_id);
+                    diags.error(self.line, Some("clothes"), value, &msg);
+                },
+                Some(rc) => buffer.clothes.push(Rc::downgrade(rc)),
+                None => {
+                    let msg = format!("No clothing has the id {}.", clth_id);
+                    diags.error(self.line, Some("clothes"), value, &msg);
+                },
+            }
+        }
+        buffer
+    }
 
-        Ok(Data { clothes, styles, outfits, ..Data::new() })
+    fn parse<T, F>(&self, key: &str, diags: &mut Diagnostics, parse: F) -> Option<T>
+        where F: Fn(&str) -> Result<T, &'static str>
+    {
+        let value = self.text(key, diags)?;
+        match parse(value) {
+            Ok(value) => Some(value),
+            Err(msg) => {
+                diags.error(self.line, Some(key), self.fields.get(key), msg);
+                None
+            },
+        }
     }
 }
 
-pub enum DataHeader {
-    Clth,
-    Outfit
+/// Returns true if `text` uses the legacy single bracket `[clth]`/`[outfit]`
+/// headers.
+fn is_legacy(text: &str) -> bool {
+    text.lines().any(|line| {
+        let line = line.trim();
+        line == "[clth]" || line == "[outfit]"
+    })
 }
 
-pub enum Value {
-    Text(String),
-    Num(i32)
+/// Returns the line numbers of the `[[name]]` (or `[name]`) headers of
+/// `text`, in order. TOML values don't carry their position, so this is how
+/// chunks read from a document get a line to report errors on.
+pub fn header_lines(text: &str, name: &str) -> Vec<u32> {
+    let headers = [format!("[[{}]]", name), format!("[{}]", name)];
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = match line.split_once('#') {
+                Some((code, _)) => code,
+                None => line,
+            };
+            headers.iter().any(|header| line.trim() == header)
+        })
+        .map(|(index, _)| index as u32 + 1)
+        .collect()
 }
 
-pub struct DataChunk{
-    pub header: DataHeader,
-    pub fields: HashMap<String, Value>,
+/// Removes the `format_version` header from `doc`, returning its value.
+fn take_version(doc: &mut toml::Value) -> Result<u32, ParseError> {
+    let value = match doc.as_table_mut() {
+        Some(root) => root.remove("format_version"),
+        None => None,
+    };
+
+    match value {
+        None => Ok(1),
+        Some(toml::Value::Integer(num)) => u32::try_from(num).map_err(|_| {
+            ParseError::new(0, String::from("Invalid 'format_version'."))
+        }),
+        Some(_) => Err(ParseError::new(0,
+            String::from("'format_version' must be an integer."))),
+    }
+}
+
+/// Reads the `draft` section, a table with an optional `clth` and an
+/// optional `outfit` table.
+pub fn from_drafts(value: toml::Value, text: &str) -> Result<Vec<DataChunk>, ParseError> {
+    let table = match value {
+        toml::Value::Table(table) => table,
+        _ => return Err(ParseError::new(0, String::from("'draft' must be a table."))),
+    };
+
+    let mut drafts = Vec::new();
+    for (key, value) in table {
+        let header = match key.as_str() {
+            "clth" => DataHeader::Clth,
+            "outfit" => DataHeader::Outfit,
+            _ => return Err(ParseError::new(0, format!("'draft.{}' is a invalid section.", key))),
+        };
+        let name = format!("draft.{}", key);
+        let line = header_lines(text, &name).first().copied().unwrap_or(0);
+        let fields = match value {
+            toml::Value::Table(fields) => fields
+                .into_iter()
+                .map(|(name, value)| (name, Value::from_toml(value)))
+                .collect(),
+            _ => return Err(ParseError::new(line, format!("'{}' must be a table.", name))),
+        };
+        drafts.push(DataChunk { header, line, fields });
+    }
+    Ok(drafts)
+}
+
+/// Converts a TOML document (`[[clth]]` and `[[outfit]]` arrays of tables)
+/// into data chunks.
+pub fn from_document(doc: toml::Value, text: &str) -> Result<Vec<DataChunk>, ParseError> {
+    let root = match doc {
+        toml::Value::Table(table) => table,
+        _ => return Err(ParseError::new(0, String::from("Expected a table."))),
+    };
+
+    let mut chunks = Vec::new();
+    for (key, value) in root {
+        let header = match key.as_str() {
+            "kind" => DataHeader::Kind,
+            "clth" => DataHeader::Clth,
+            "outfit" => DataHeader::Outfit,
+            "sale" => DataHeader::Sale,
+            "donation" => DataHeader::Donation,
+            _ => return Err(ParseError::new(0, format!("'{}' is a invalid section.", key))),
+        };
+
+        let tables = match value {
+            toml::Value::Array(items) => items,
+            table @ toml::Value::Table(_) => vec![table],
+            _ => return Err(ParseError::new(0, format!("'{}' must be a table.", key))),
+        };
+
+        let lines = header_lines(text, header.name());
+        for (index, table) in tables.into_iter().enumerate() {
+            let line = lines.get(index).copied().unwrap_or(0);
+            let table = match table {
+                toml::Value::Table(table) => table,
+                _ => return Err(ParseError::new(line,
+                    format!("Every '{}' entry must be a table.", key))),
+            };
+
+            let fields = table
+                .into_iter()
+                .map(|(name, value)| (name, Value::from_toml(value)))
+                .collect();
+            chunks.push(DataChunk { header, line, fields });
+        }
+    }
+    Ok(chunks)
 }
 
 pub fn parse(chunk: &str) -> Result<DataChunk, String> {
@@ -244,7 +1341,7 @@ pub fn parse(chunk: &str) -> Result<DataChunk, String> {
             let end = value.len() - 1; 
             Value::Text(String::from(&value[1..end]))
         } else {
-            let num: i32 = match value.parse() {
+            let num: i64 = match value.parse() {
                 Ok(value) => value,
                 Err(_) => return Err(format!("Syntax error: '{}'", value))
             };
@@ -252,7 +1349,7 @@ pub fn parse(chunk: &str) -> Result<DataChunk, String> {
         };
         fields.insert(String::from(key), value); }
 
-    Ok(DataChunk { header, fields })
+    Ok(DataChunk { header, line: 0, fields })
 }
 
 pub fn into_chunks(text: &str) -> Result<Vec<DataChunk>, ParseError> { 
@@ -271,7 +1368,7 @@ pub fn into_chunks(text: &str) -> Result<Vec<DataChunk>, ParseError> {
         } else if chunk.is_some() {
             let value = chunk.take().unwrap(); 
             match parse(&value) {
-                Ok(data) => chunks.push(data),
+                Ok(data) => chunks.push(DataChunk { line: header_line, ..data }),
                 Err(msg) => return Err(ParseError::new(header_line, msg)),
             }
         }
@@ -279,7 +1376,7 @@ pub fn into_chunks(text: &str) -> Result<Vec<DataChunk>, ParseError> {
 
     if let Some(value) = chunk.take() {
         match parse(&value) {
-            Ok(data) => chunks.push(data),
+            Ok(data) => chunks.push(DataChunk { line: header_line, ..data }),
             Err(msg) => return Err(ParseError::new(header_line, msg)),
         }
     }
@@ -334,6 +1431,92 @@ pub mod tests {
         foot = 1
     ";
 
+    #[test]
+    pub fn resolve_data_path() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |key: &str| vars
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| OsString::from(value))
+        };
+
+        let all = env(&[("RECLOSET_DATA", "/env.toml"),
+            ("XDG_CONFIG_HOME", "/xdg"), ("HOME", "/home/me")]);
+        assert_eq!(PathBuf::from("/flag.toml"),
+            data_path(Some("/flag.toml"), all).unwrap());
+        assert_eq!(PathBuf::from("/env.toml"), data_path(None, all).unwrap());
+
+        let xdg = env(&[("RECLOSET_DATA", ""), ("XDG_CONFIG_HOME", "/xdg"),
+            ("HOME", "/home/me")]);
+        assert_eq!(PathBuf::from("/xdg/recloset/data.toml"),
+            data_path(None, xdg).unwrap());
+
+        let home = env(&[("HOME", "/home/me")]);
+        assert_eq!(PathBuf::from("/home/me/.config/recloset/data.toml"),
+            data_path(None, home).unwrap());
+
+        assert!(data_path(None, env(&[])).is_err());
+    }
+
+    /// The closet of the latest fixture, shared by the tests of every module.
+    pub fn closet() -> Data {
+        read_data(include_str!("../tests/fixtures/v12.toml"), false).unwrap().0
+    }
+
+    pub fn tmp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("recloset-{}-{}", name, std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    pub fn save_keeps_backup() {
+        let dir = tmp_dir("backup");
+        let path = dir.join("data.toml");
+
+        save(&path, CLTH1).unwrap();
+        assert!(!backup_path(&path).exists());
+        save(&path, CLTH2).unwrap();
+
+        assert_eq!(CLTH2, fs::read_to_string(&path).unwrap());
+        assert_eq!(CLTH1, fs::read_to_string(backup_path(&path)).unwrap());
+        assert!(!sibling_path(&path, ".tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn load_recovers_from_backup() {
+        let dir = tmp_dir("recover");
+        let path = dir.join("data.toml");
+
+        assert!(matches!(load(&path, false).unwrap().source, Source::New));
+
+        save(&path, CLTH1).unwrap();
+        save(&path, "[clth]\nid = broken").unwrap();
+
+        let loaded = load(&path, false).unwrap();
+        assert!(loaded.data.clothes.get(0).is_some());
+        assert!(matches!(loaded.source, Source::Backup { .. }));
+        assert_eq!("[clth]\nid = broken", fs::read_to_string(&path).unwrap());
+
+        let moved_to = restore_backup(&path).unwrap();
+        assert_eq!(sibling_path(&path, ".damaged"), moved_to);
+        assert_eq!("[clth]\nid = broken", fs::read_to_string(&moved_to).unwrap());
+        assert_eq!(CLTH1, fs::read_to_string(&path).unwrap());
+
+        fs::write(&path, "[[clth]]\nid = 0\nkind = \"chest\"\nsize = \"huge\"").unwrap();
+        let err = load(&path, false).err().unwrap();
+        assert!(err.contains("size"), "{}", err);
+        assert!(fs::read_to_string(&path).unwrap().contains("huge"));
+
+        fs::write(backup_path(&path), "[clth]\nid = broken").unwrap();
+        fs::write(&path, "[outfit]\nchest = 0").unwrap();
+        assert!(load(&path, false).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     pub fn text_to_datachunk() {
         let result = match super::parse(TEXT1) {
@@ -343,15 +1526,15 @@ pub mod tests {
 
         assert!(matches!(result.header, DataHeader::Clth));
 
-        let id = match &*result.fields.get("id").expect("Id not found.") {
+        let id = match result.fields.get("id").expect("Id not found.") {
             Value::Num(v) => v,
-            Value::Text(_) => panic!("Id interpreted as string!"),
+            _ => panic!("Id interpreted as string!"),
         };
         assert_eq!(2, *id);
 
-        let kind = match &*result.fields.get("kind").expect("Kind not found.") {
+        let kind = match result.fields.get("kind").expect("Kind not found.") {
             Value::Text(v) => v,
-            Value::Num(_) => panic!("Kind interpreted as number!"),
+            _ => panic!("Kind interpreted as number!"),
         };
         assert_eq!("chest", kind);
     }
@@ -374,20 +1557,323 @@ pub mod tests {
         assert_eq!(3, fdata.clth_chunks.len());
         assert_eq!(0, fdata.outfit_chunks.len());
 
-        let (clths, stls) = fdata.extract_clths().unwrap();
+        let mut diags = Diagnostics::new();
+        let (clths, stls) = fdata.extract_clths(&Kinds::new(), &mut diags, &mut Quarantine::new());
+        assert!(diags.is_empty());
         assert!(
             clths.get(0).is_some() && clths.get(1).is_some() &&
             clths.get(2).is_some());
         assert!(stls.get("summer").is_some());
     }
 
+    const DOCUMENT: &str = r##"
+        # my closet
+        outfit = [ { chest = 0, leg = 2, foot = 1 } ]
+
+        [[clth]]
+        id = 0 # first one
+        kind = "chest"
+        sex = "male"
+        size = "L"
+        color = "FF00EE"
+        target = "Sale for $20.75"
+        purchase_date = "2022-08-15"
+        style = "say \"hi\" # not a comment"
+
+        [[clth]]
+        id = 1
+        kind = "foot"
+        sex = "male"
+        size = "M"
+        color = "FF00EE"
+        target = "Keep"
+        purchase_date = "2022-08-15"
+        style = 'say "hi" # not a comment'
+
+        [[clth]]
+        id = 2
+        kind = "leg"
+        sex = "male"
+        size = "M"
+        color = "FF00EE"
+        target = "Donation"
+        purchase_date = "2022-08-15"
+        style = "say \"hi\" # not a comment"
+    "##;
+
+    #[test]
+    pub fn read_toml_document() {
+        let fdata = FileData::from(DOCUMENT).unwrap();
+        let lines: Vec<u32> = fdata.clth_chunks.iter().map(|c| c.line).collect();
+        assert_eq!(vec![5, 15, 25], lines);
+
+        let data = fdata.to_data().unwrap();
+        assert!(data.styles.get("say \"hi\" # not a comment").is_some());
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+    }
+
+    #[test]
+    pub fn toml_round_trip() {
+        let data = FileData::from(DOCUMENT).unwrap().to_data().unwrap();
+        let text = data.to_toml();
+        let again = FileData::from(&text).unwrap().to_data().unwrap();
+        assert_eq!(text, again.to_toml());
+        assert_eq!(3, again.clothes.list.len());
+        assert_eq!(vec![ vec![0, 2, 1] ], again.outfits.to_id_matrix());
+    }
+
+    #[test]
+    pub fn legacy_layout_is_converted() {
+        let legacy = [CLTH1, CLTH2, CLTH3, OUTFIT].join("\n\n");
+        let data = FileData::from(&legacy).unwrap().to_data().unwrap();
+        let text = data.to_toml();
+        assert!(text.contains("[[clth]]") && text.contains("[[outfit]]"));
+
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+        assert_eq!(vec![ vec![0, 2, 1] ], data.outfits.to_id_matrix());
+    }
+
+    #[test]
+    pub fn report_toml_error_line() {
+        let err = FileData::from("[[clth]]\nid = 0\nkind = \"chest\n").err().unwrap();
+        assert_eq!(3, err.line);
+    }
+
+    /// One file per historical format version, all holding the same closet.
+    const FIXTURES: [(&str, &str); 13] = [
+        ("v1-legacy", include_str!("../tests/fixtures/v1-legacy.toml")),
+        ("v1", include_str!("../tests/fixtures/v1.toml")),
+        ("v2", include_str!("../tests/fixtures/v2.toml")),
+        ("v3", include_str!("../tests/fixtures/v3.toml")),
+        ("v4", include_str!("../tests/fixtures/v4.toml")),
+        ("v5", include_str!("../tests/fixtures/v5.toml")),
+        ("v6", include_str!("../tests/fixtures/v6.toml")),
+        ("v7", include_str!("../tests/fixtures/v7.toml")),
+        ("v8", include_str!("../tests/fixtures/v8.toml")),
+        ("v9", include_str!("../tests/fixtures/v9.toml")),
+        ("v10", include_str!("../tests/fixtures/v10.toml")),
+        ("v11", include_str!("../tests/fixtures/v11.toml")),
+        ("v12", include_str!("../tests/fixtures/v12.toml")),
+    ];
+
+    #[test]
+    pub fn migrate_fixtures() {
+        let (_, latest) = FIXTURES.last().unwrap();
+        let expected = FileData::from(latest).unwrap().to_data().unwrap().to_toml();
+        assert_eq!(*latest, expected, "The newest fixture must be current.");
+
+        for (name, text) in FIXTURES {
+            let fdata = FileData::from(text).expect(name);
+            assert_eq!(FORMAT_VERSION, fdata.version, "{}", name);
+            let data = fdata.to_data().expect(name);
+            assert_eq!(expected, data.to_toml(), "{}", name);
+        }
+    }
+
+    #[test]
+    pub fn reject_unknown_version() {
+        let text = format!("format_version = {}", FORMAT_VERSION + 1);
+        assert!(FileData::from(&text).is_err());
+        assert!(FileData::from("format_version = \"2\"").is_err());
+    }
+
+    const BROKEN: &str = "
+        [[clth]]
+        id = 0
+        kind = \"hat\"
+        sex = \"male\"
+        size = \"L\"
+        color = \"FF00EE\"
+        target = \"Keep\"
+        purchase_date = \"2022-08-15\"
+        style = \"summer\"
+        colour = \"red\"
+
+        [[clth]]
+        id = 1.5
+        sex = \"male\"
+        size = \"XXXXL\"
+        color = \"FF00EE\"
+        target = \"Keep\"
+        purchase_date = \"2022-08-15\"
+        style = \"summer\"
+
+        [[outfit]]
+        chest = 0
+        leg = 7
+        foot = 1
+    ";
+
+    #[test]
+    pub fn collect_every_problem() {
+        let fdata = FileData::from(BROKEN).unwrap();
+        let diags = fdata.to_data().err().unwrap();
+
+        let found: Vec<_> = diags.list
+            .iter()
+            .map(|diag| (diag.severity, diag.line, diag.field.as_deref(),
+                diag.value.as_deref()))
+            .collect();
+        assert_eq!(vec![
+            (Severity::Warning, 2, Some("colour"), Some("\"red\"")),
+            (Severity::Error, 2, Some("kind"), Some("\"hat\"")),
+            (Severity::Error, 13, Some("id"), Some("1.5")),
+            (Severity::Error, 13, Some("kind"), None),
+            (Severity::Error, 13, Some("size"), Some("\"XXXXL\"")),
+            (Severity::Error, 22, Some("clothes"), Some("[0, 7, 1]")),
+        ], found);
+        assert_eq!("Error on line 2 (kind = \"hat\"): Invalid kind.",
+            diags.list[1].to_string());
+    }
+
+    #[test]
+    pub fn warnings_do_not_fail() {
+        let text = format!("{}\n\nnote = \"bought online\"", CLTH1.replace("[clth]", "[[clth]]"));
+        let (data, diags) = FileData::from(&text).unwrap().load();
+        assert!(!diags.has_errors() && !diags.is_empty());
+        assert!(data.clothes.get(0).is_some());
+    }
+
+    #[test]
+    pub fn reject_duplicate_clth_ids() {
+        let text = [CLTH1, CLTH1].join("\n\n");
+        let diags = FileData::from(&text).unwrap().to_data().err().unwrap();
+        assert_eq!(1, diags.list.len());
+        assert_eq!(Some("id"), diags.list[0].field.as_deref());
+    }
+
+    #[test]
+    pub fn keep_outfit_ids() {
+        let text = include_str!("../tests/fixtures/v12.toml")
+            .replace("[[outfit]]\nid = 0", "[[outfit]]\nid = 4");
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+        assert!(data.outfits.get(4).is_some());
+        assert_eq!(text, data.to_toml());
+
+        let text = format!("{}\n[[outfit]]\nid = 4\nclothes = [0, 2, 1]\n", text);
+        let diags = FileData::from(&text).unwrap().to_data().err().unwrap();
+        assert_eq!(Some("id"), diags.list[0].field.as_deref());
+        assert_eq!("An outfit with the same id already exists.", diags.list[0].msg);
+    }
+
+    #[test]
+    pub fn keep_archived_clothes() {
+        let text = include_str!("../tests/fixtures/v12.toml")
+            .replacen("status = \"Active\"", "status = \"Sold on 2023-01-02\"", 1);
+        let diags = FileData::from(&text).unwrap().to_data().err().unwrap();
+        assert_eq!(Some("clothes"), diags.list[0].field.as_deref());
+        assert_eq!("Clothing 0 is archived.", diags.list[0].msg);
+
+        let text = text.split("[[outfit]]").next().unwrap().trim_end().to_string() + "\n";
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+        let date = NaiveDate::from_ymd(2023, 1, 2);
+        assert_eq!(Status::Sold(date), data.clothes.get(0).unwrap().borrow().status);
+        assert_eq!(2, data.clothes.active().list.len());
+        assert_eq!(text, data.to_toml());
+    }
+
+    #[test]
+    pub fn keep_kinds() {
+        // the leg clothing becomes a dress, so its outfit has to go.
+        let text = include_str!("../tests/fixtures/v12.toml").replacen("\n\n", "\n\n\
+            [[kind]]\nname = \"Dress\"\nslot = \"full-body\"\n\n", 1)
+            .replacen("kind = \"Leg\"", "kind = \"dress\"", 1);
+        let text = text.split("[[outfit]]").next().unwrap().trim_end().to_string() + "\n";
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+        assert_eq!(Slot::FullBody, data.kinds.parse("dress").unwrap().slot);
+        assert_eq!(1, data.kinds.custom().len());
+        assert_eq!("Dress", data.clothes.get(2).unwrap().borrow().kind.name);
+        assert_eq!(text.replace("\"dress\"", "\"Dress\""), data.to_toml());
+
+        let twice = text.replacen("[[clth]]", "[[kind]]\nname = \"DRESS\"\nslot = \"top\"\n\n\
+            [[clth]]", 1);
+        let (data, diags) = FileData::from(&twice).unwrap().read(true).unwrap();
+        assert_eq!("A kind with the same name already exists.", diags.list[0].msg);
+        assert_eq!(1, data.quarantine.len());
+        assert_eq!(Slot::FullBody, data.kinds.parse("dress").unwrap().slot);
+    }
+
+    #[test]
+    pub fn keep_sizes() {
+        let text = include_str!("../tests/fixtures/v12.toml").replacen("\n\n", "\n\n\
+            [[kind]]\nname = \"Dress\"\nslot = \"full-body\"\nsizes = \"numeric\"\n\n\
+            [[kind]]\nname = \"Boot\"\nslot = \"footwear\"\n\n", 1)
+            .replacen("size = \"M\"", "size = \"9.5 US\"", 1)
+            .replacen("size = \"XS\"", "size = \"32x34\"", 1);
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+        assert_eq!(SizeSystem::Numeric, data.kinds.parse("dress").unwrap().sizes);
+        assert_eq!(SizeSystem::Shoe, data.kinds.parse("boot").unwrap().sizes);
+        assert_eq!(Size::Waist(32, 34), data.clothes.get(2).unwrap().borrow().size);
+        assert_eq!(text, data.to_toml());
+
+        let text = text.replacen("size = \"L\"", "size = \"42 EU\"", 1);
+        let diags = FileData::from(&text).unwrap().to_data().err().unwrap();
+        assert_eq!(Some("size"), diags.list[0].field.as_deref());
+        assert_eq!("The size doesn't fit the kind.", diags.list[0].msg);
+    }
+
+    #[test]
+    pub fn keep_large_amounts() {
+        use crate::money::{ Currency, Money, MAX_CENTS };
+        use crate::store::Store;
+        let mut data = closet();
+        let date = NaiveDate::from_ymd(2023, 1, 2);
+        let idr = Currency::new("IDR").unwrap();
+        crate::set_cost(&mut data, 0, Some(Money::usd(3_000_000_000))).unwrap();
+        crate::set_cost(&mut data, 2, Some(Money::new(MAX_CENTS, idr.clone()))).unwrap();
+        crate::sell_clth(&mut data, crate::sales::Sale { clth: 0, date,
+            price: Money::usd(3_000_000_000), channel: None }).unwrap();
+        crate::sell_clth(&mut data, crate::sales::Sale { clth: 2, date,
+            price: Money::new(MAX_CENTS, idr), channel: None }).unwrap();
+
+        let again = read_data(&data.to_toml(), false).unwrap().0;
+        assert_eq!(data.to_toml(), again.to_toml());
+        assert_eq!(Some(Money::usd(3_000_000_000)), again.clothes.get(0).unwrap().borrow().cost);
+        assert_eq!(data.sales.list, again.sales.list);
+
+        let mut store = crate::sqlite::SqliteStore::in_memory().unwrap();
+        store.save(&data).unwrap();
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+    }
+
+    #[test]
+    pub fn keep_drafts() {
+        let text = format!("{}[draft.clth]\nkind = \"Leg\"\nprice = \"19.90 EUR\"\n\n\
+            [draft.outfit]\nclothes = [0, 1]\n", include_str!("../tests/fixtures/v12.toml"));
+        let data = FileData::from(&text).unwrap().to_data().unwrap();
+
+        let clth = data.cache.clth.as_ref().unwrap();
+        assert_eq!(Some("Leg"), clth.kind.as_ref().map(|kind| kind.name.as_str()));
+        assert_eq!(Some(Money::new(1990, Currency::new("EUR").unwrap())), clth.price);
+        assert!(clth.sex.is_none());
+        let outfit = data.cache.outfit.as_ref().unwrap();
+        assert_eq!(vec![Slot::Top, Slot::Footwear], outfit.slots());
+        assert_eq!(text, data.to_toml());
+
+        // broken drafts only lose the broken fields.
+        // as do the prices in cents of older drafts.
+        let old = text.replace("price = \"19.90 EUR\"", "price = 1990");
+        let data = FileData::from(&old).unwrap().to_data().unwrap();
+        assert_eq!(Some(Money::usd(1990)), data.cache.clth.as_ref().unwrap().price);
+
+        let text = text.replace("price = \"19.90 EUR\"", "price = -1")
+            .replace("clothes = [0, 1]", "clothes = [0, 9]");
+        let (data, diags) = FileData::from(&text).unwrap().load();
+        assert!(!diags.has_errors());
+        assert_eq!(2, diags.list.len());
+        assert!(data.cache.clth.as_ref().unwrap().price.is_none());
+        assert_eq!(1, data.cache.outfit.as_ref().unwrap().clothes.len());
+    }
+
     #[test]
     pub fn create_outfits() {
         let text = [CLTH1, CLTH2, CLTH3, OUTFIT].join("\n\n");
         let fdata = FileData::from(&text).unwrap();
 
-        let (clths, _) = fdata.extract_clths().unwrap();
-        let outfits = fdata.extract_outfits(&clths).unwrap();
-        assert_eq!(vec![ [0,2,1] ], outfits.to_id_matrix());
+        let mut diags = Diagnostics::new();
+        let (clths, _) = fdata.extract_clths(&Kinds::new(), &mut diags, &mut Quarantine::new());
+        let outfits = fdata.extract_outfits(&clths, &mut diags, &mut Quarantine::new());
+        assert!(diags.is_empty());
+        assert_eq!(vec![ vec![0, 2, 1] ], outfits.to_id_matrix());
     }
 }
@@ -0,0 +1,281 @@
+//! Persistence backends of the closet.
+//!
+//! A `Store` loads the whole closet once and then persists the changes made
+//! to it. The text store rewrites the data file on every update, while the
+//! SQLite store (see `sqlite`) only touches the rows that changed.
+
+use std::fs;
+use std::path::{ Path, PathBuf };
+
+use crate::Data;
+use crate::sqlite::SqliteStore;
+use crate::storage::{ self, Loaded };
+
+/// A change made to the closet, to be persisted by a `Store`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The user-defined kind of this name was added or removed.
+    Kind(String),
+    AddClth(u32),
+    UpdateClth(u32),
+    /// Removes the clothing along with every outfit using it.
+    RemoveClth(u32),
+    AddOutfit(u32),
+    RemoveOutfit(u32),
+    /// The sale of the clothing was recorded or removed.
+    Sale(u32),
+    /// The donation was recorded or removed.
+    Donation(u32),
+    /// The quarantined records changed.
+    Quarantine,
+    /// The unfinished clothing or outfit changed.
+    Draft,
+    /// The whole closet changed (e.g. it was imported).
+    All,
+}
+
+pub trait Store {
+    /// Loads the whole closet. See `storage::read_data` for `lenient`.
+    fn load(&mut self, lenient: bool) -> Result<Loaded, String>;
+
+    /// Writes the whole closet, replacing what was stored.
+    fn save(&mut self, data: &Data) -> Result<(), String>;
+
+    /// Persists `changes`, made to the stored closet to get `data`. Stores
+    /// that can't update a part of the closet save all of it.
+    fn update(&mut self, data: &Data, changes: &[Change]) -> Result<(), String> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        self.save(data)
+    }
+
+    /// Replaces a damaged closet with the backup it was loaded from (see
+    /// `storage::Source::Backup`), returning where the damaged one was moved
+    /// to. Stores without backups, or that must not be written, do nothing.
+    fn restore_backup(&mut self) -> Result<Option<PathBuf>, String> {
+        Ok(None)
+    }
+
+    /// Persists the pending changes of `data`, clearing them. On failure
+    /// they are kept, to be retried by the next commit.
+    fn commit(&mut self, data: &mut Data) -> Result<(), String> {
+        self.update(data, &data.changes)?;
+        data.changes.clear();
+        Ok(())
+    }
+}
+
+/// The data file store.
+pub struct TextStore {
+    pub path: PathBuf,
+    /// Fingerprint of the data file as last loaded or saved.
+    fingerprint: Option<u64>,
+}
+
+impl TextStore {
+    pub fn new(path: &Path) -> TextStore {
+        TextStore { path: path.to_path_buf(), fingerprint: None }
+    }
+
+    fn fingerprint(&self) -> Result<Option<u64>, String> {
+        storage::fingerprint(&self.path)
+            .map_err(|err| format!("Can't read {}: {}", self.path.display(), err))
+    }
+}
+
+impl Store for TextStore {
+    fn load(&mut self, lenient: bool) -> Result<Loaded, String> {
+        let loaded = storage::load(&self.path, lenient)?;
+        self.fingerprint = self.fingerprint()?;
+        Ok(loaded)
+    }
+
+    /// Refuses to overwrite the data file if it changed since it was loaded.
+    fn save(&mut self, data: &Data) -> Result<(), String> {
+        if self.fingerprint()? != self.fingerprint {
+            return Err(format!("{} was changed by another program since it was \
+                loaded. Restart recloset to load those changes; yours were not \
+                saved.", self.path.display()));
+        }
+
+        // the file is unchanged since it was loaded, so its comments are ours.
+        let old = fs::read_to_string(&self.path).unwrap_or_default();
+        let text = storage::keep_comments(&old, &data.to_toml());
+        storage::save(&self.path, &text).map_err(|err| err.to_string())?;
+        self.fingerprint = self.fingerprint()?;
+        Ok(())
+    }
+
+    fn restore_backup(&mut self) -> Result<Option<PathBuf>, String> {
+        let moved_to = storage::restore_backup(&self.path)?;
+        self.fingerprint = self.fingerprint()?;
+        Ok(Some(moved_to))
+    }
+}
+
+/// Wraps a store that must not be written to, e.g. because another process
+/// holds the closet lock (see `lock`).
+pub struct ReadOnlyStore {
+    inner: Box<dyn Store>,
+}
+
+impl ReadOnlyStore {
+    pub fn new(inner: Box<dyn Store>) -> ReadOnlyStore {
+        ReadOnlyStore { inner }
+    }
+}
+
+impl Store for ReadOnlyStore {
+    fn load(&mut self, lenient: bool) -> Result<Loaded, String> {
+        self.inner.load(lenient)
+    }
+
+    fn save(&mut self, _: &Data) -> Result<(), String> {
+        Err(String::from("The closet is open read-only; changes can't be saved."))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    Text,
+    Sqlite,
+}
+
+impl Backend {
+    /// Picks the backend of a data file by its extension: `.db`, `.sqlite`
+    /// and `.sqlite3` files are SQLite databases, anything else is text.
+    pub fn of(path: &Path) -> Backend {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("db" | "sqlite" | "sqlite3") => Backend::Sqlite,
+            _ => Backend::Text,
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Backend, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "text" | "toml" => Ok(Backend::Text),
+            "sqlite" => Ok(Backend::Sqlite),
+            _ => Err("Invalid store! Help: valid stores are text and sqlite."),
+        }
+    }
+}
+
+/// Opens the store of the data file at `path`.
+pub fn open(backend: Backend, path: &Path) -> Result<Box<dyn Store>, String> {
+    match backend {
+        Backend::Text => Ok(Box::new(TextStore::new(path))),
+        Backend::Sqlite => Ok(Box::new(SqliteStore::open(path)?)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    struct BrokenStore;
+
+    impl Store for BrokenStore {
+        fn load(&mut self, _: bool) -> Result<Loaded, String> {
+            Err(String::from("broken"))
+        }
+
+        fn save(&mut self, _: &Data) -> Result<(), String> {
+            Err(String::from("broken"))
+        }
+    }
+
+    #[test]
+    fn keep_changes_until_saved() {
+        let mut data = Data::new();
+        assert!(BrokenStore.commit(&mut data).is_ok());
+
+        data.changes.push(Change::All);
+        assert!(BrokenStore.commit(&mut data).is_err());
+        assert_eq!(vec![Change::All], data.changes);
+    }
+
+    #[test]
+    fn refuse_external_changes() {
+        let dir = crate::storage::tests::tmp_dir("external");
+        let path = dir.join("data.toml");
+        let mut store = TextStore::new(&path);
+        let mut data = store.load(false).unwrap().data;
+
+        data.changes.push(Change::All);
+        store.commit(&mut data).unwrap();
+        data.changes.push(Change::All);
+        store.commit(&mut data).unwrap();
+
+        std::fs::write(&path, "format_version = 3\n").unwrap();
+        data.changes.push(Change::All);
+        assert!(store.commit(&mut data).is_err());
+        assert_eq!("format_version = 3\n", std::fs::read_to_string(&path).unwrap());
+
+        let mut read_only = ReadOnlyStore::new(Box::new(TextStore::new(&path)));
+        assert!(read_only.load(false).is_ok());
+        assert!(read_only.commit(&mut Data::new()).is_ok());
+        assert!(read_only.commit(&mut data).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn restore_backup_unless_read_only() {
+        let dir = crate::storage::tests::tmp_dir("restore");
+        let path = dir.join("data.toml");
+        let text = include_str!("../tests/fixtures/v12.toml");
+        std::fs::write(storage::backup_path(&path), text).unwrap();
+        std::fs::write(&path, "[clth]\nid = broken").unwrap();
+
+        let mut read_only = ReadOnlyStore::new(Box::new(TextStore::new(&path)));
+        assert!(matches!(read_only.load(false).unwrap().source, storage::Source::Backup { .. }));
+        assert_eq!(None, read_only.restore_backup().unwrap());
+        assert_eq!("[clth]\nid = broken", std::fs::read_to_string(&path).unwrap());
+
+        let mut store = TextStore::new(&path);
+        let mut data = store.load(false).unwrap().data;
+        assert!(store.restore_backup().unwrap().unwrap().exists());
+        assert_eq!(text, std::fs::read_to_string(&path).unwrap());
+        data.changes.push(Change::All);
+        store.commit(&mut data).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn keep_comments_on_save() {
+        let dir = crate::storage::tests::tmp_dir("comments");
+        let path = dir.join("data.toml");
+        let text = "# my closet\nformat_version = 12\n\n\
+            # the jacket\n[[clth]]\nid = 0 # first one\nkind = \"chest\"\n\
+            sex = \"male\"\nsize = \"L\"\ncolor = \"FF00EE\"\ntarget = \"Keep\"\n\
+            purchase_date = \"2022-08-15\"\nstyle = \"summer\"\nstatus = \"Active\"\n\n\
+            # the end\n";
+        std::fs::write(&path, text).unwrap();
+
+        let mut store = TextStore::new(&path);
+        let mut data = store.load(false).unwrap().data;
+        crate::wear_clth(&mut data, 0).unwrap();
+        store.commit(&mut data).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with("# my closet\nformat_version = 12\n"), "{}", saved);
+        assert!(saved.contains("\n# the jacket\n[[clth]]\nid = 0 # first one\n"), "{}", saved);
+        assert!(saved.contains("wears = 1") && saved.ends_with("\n# the end\n"), "{}", saved);
+        assert_eq!(data.to_toml(), store.load(false).unwrap().data.to_toml());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn pick_backend() {
+        assert_eq!(Backend::Sqlite, Backend::of(Path::new("/tmp/closet.db")));
+        assert_eq!(Backend::Text, Backend::of(Path::new("/tmp/data.toml")));
+        assert_eq!(Backend::Text, Backend::of(Path::new("closet")));
+        assert_eq!(Ok(Backend::Sqlite), Backend::from_str("SQLite"));
+        assert!(Backend::from_str("csv").is_err());
+    }
+}